@@ -2,10 +2,16 @@
 // xmlsec1 headers include
 //
 #include <xmlsec/app.h>
+#include <xmlsec/base64.h>
 #include <xmlsec/crypto.h>
+#include <xmlsec/errors.h>
+#include <xmlsec/io.h>
 #include <xmlsec/keys.h>
+#include <xmlsec/keysmngr.h>
+#include <xmlsec/membuf.h>
 #include <xmlsec/templates.h>
 #include <xmlsec/transforms.h>
 #include <xmlsec/xmldsig.h>
+#include <xmlsec/xmlenc.h>
 #include <xmlsec/xmlsec.h>
 #include <xmlsec/xmltree.h>
@@ -13,14 +13,27 @@ use std::process::Command;
 
 const BINDINGS: &str = "bindings.rs";
 
+/// Crypto backends of xmlsec1, by the cargo feature selecting them and the libraries they link against.
+const BACKENDS: [(&str, &[&str]); 4] = [
+    ("openssl", &["xmlsec1-openssl", "ssl", "crypto"]),
+    ("nss",     &["xmlsec1-nss", "nss3", "nssutil3", "smime3", "ssl3", "plds4", "plc4", "nspr4"]),
+    ("gnutls",  &["xmlsec1-gnutls", "gnutls"]),
+    ("gcrypt",  &["xmlsec1-gcrypt", "gcrypt"]),
+];
+
 
 fn main()
 {
-    println!("cargo:rustc-link-lib=xmlsec1-openssl");  // -lxmlsec1-openssl
+    let (backend, libs) = select_backend();
+
+    for lib in libs {
+        println!("cargo:rustc-link-lib={}", lib);   // -lxmlsec1-<backend> and the crypto library itself
+    }
+
     println!("cargo:rustc-link-lib=xmlsec1");          // -lxmlsec1
     println!("cargo:rustc-link-lib=xml2");             // -lxml2
-    println!("cargo:rustc-link-lib=ssl");              // -lssl
-    println!("cargo:rustc-link-lib=crypto");           // -lcrypto
+
+    detect_xmlsec_features(backend);
 
     let path_out      = PathBuf::from(env::var("OUT_DIR").unwrap());
     let path_bindings = path_out.join(BINDINGS);
@@ -28,13 +41,13 @@ fn main()
     if !path_bindings.exists()
     {
         PkgConfig::new()
-            .probe("xmlsec1")
-            .expect("Could not find xmlsec1 using pkg-config");
+            .probe(&format!("xmlsec1-{}", backend))
+            .expect("Could not find xmlsec1 crypto backend using pkg-config");
 
         let bindbuild = BindgenBuilder::default()
             .header("bindings.h")
-            .clang_args(fetch_xmlsec_config_flags())
-            .clang_args(fetch_xmlsec_config_libs())
+            .clang_args(fetch_xmlsec_config_flags(backend))
+            .clang_args(fetch_xmlsec_config_libs(backend))
             .layout_tests(true)
             .formatter(BindgenFormatter::default())
             .generate_comments(true);
@@ -48,9 +61,50 @@ fn main()
 }
 
 
-fn fetch_xmlsec_config_flags() -> Vec<String>
+/// Picks the crypto backend enabled through cargo features, of which there has to be exactly one.
+fn select_backend() -> (&'static str, &'static [&'static str])
+{
+    let enabled = BACKENDS.iter()
+        .filter(|(feature, _)| env::var_os(format!("CARGO_FEATURE_{}", feature.to_uppercase())).is_some())
+        .collect::<Vec<_>>();
+
+    match enabled.as_slice()
+    {
+        [backend] => **backend,
+        []        => panic!("No xmlsec crypto backend selected. Enable one of the features: openssl, nss, gnutls, gcrypt"),
+        _         => panic!("Only one xmlsec crypto backend may be selected. The features openssl, nss, gnutls and \
+                             gcrypt are mutually exclusive (disable default features to pick other than openssl)"),
+    }
+}
+
+
+/// Tells the crate which of the xmlsec1 functions it may rely upon depend on the version installed. Namely,
+/// `xmlSecDSigCtxReset` is only exported starting with 1.3.
+fn detect_xmlsec_features(backend: &str)
+{
+    println!("cargo::rustc-check-cfg=cfg(xmlsec_dsig_ctx_reset)");
+
+    let library = PkgConfig::new()
+        .cargo_metadata(false)
+        .probe(&format!("xmlsec1-{}", backend))
+        .expect("Could not find xmlsec1 crypto backend using pkg-config");
+
+    let mut version = library.version.split('.')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+
+    let major = version.next().unwrap_or(0);
+    let minor = version.next().unwrap_or(0);
+
+    if (major, minor) >= (1, 3) {
+        println!("cargo:rustc-cfg=xmlsec_dsig_ctx_reset");
+    }
+}
+
+
+fn fetch_xmlsec_config_flags(backend: &str) -> Vec<String>
 {
     let out = Command::new("xmlsec1-config")
+        .arg(format!("--crypto={}", backend))
         .arg("--cflags")
         .output()
         .expect("Failed to get --cflags from xmlsec1-config. Is xmlsec1 installed?")
@@ -60,9 +114,10 @@ fn fetch_xmlsec_config_flags() -> Vec<String>
 }
 
 
-fn fetch_xmlsec_config_libs() -> Vec<String>
+fn fetch_xmlsec_config_libs(backend: &str) -> Vec<String>
 {
     let out = Command::new("xmlsec1-config")
+        .arg(format!("--crypto={}", backend))
         .arg("--libs")
         .output()
         .expect("Failed to get --libs from xmlsec1-config. Is xmlsec1 installed?")
@@ -77,9 +132,7 @@ fn args_from_output(args: Vec<u8>) -> Vec<String>
     let decoded = String::from_utf8(args)
         .expect("Got invalid UTF8 from xmlsec1-config");
 
-    let args = decoded.split_whitespace()
+    decoded.split_whitespace()
         .map(|p| p.to_owned())
-        .collect::<Vec<String>>();
-
-    args
+        .collect::<Vec<String>>()
 }
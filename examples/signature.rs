@@ -5,6 +5,7 @@
 use xmlsec::XmlSecTemplateBuilder;
 use xmlsec::XmlSecDocumentTemplating;
 use xmlsec::XmlSecCanonicalizationMethod;
+#[allow(deprecated)]
 use xmlsec::XmlSecSignatureMethod;
 
 use xmlsec::XmlSecKey;
@@ -30,6 +31,7 @@ fn main()
 }
 
 
+#[allow(deprecated)]
 fn create_template(doc: &XmlDocument)
 {
     doc.template()
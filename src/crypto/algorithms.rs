@@ -0,0 +1,436 @@
+//!
+//! Typed Algorithms of Signature and Encryption Templates
+//!
+//! Each kind of algorithm gets its own type, so that for instance a digest cannot be passed where a signature method
+//! is expected. Every crypto backend maps them onto its own transforms. The deprecated [`XmlSecSignatureMethod`]
+//! mixing all of them converts from them, and into them failing with an error when not of the kind converted into.
+//!
+use crate::bindings;
+
+use crate::XmlSecError;
+
+#[allow(deprecated)]
+use crate::XmlSecSignatureMethod;
+
+use super::transform_supported;
+
+
+/// Signature methods of `<ds:SignedInfo/>`, as specified by the XML standard. Which ones are available depends on
+/// the crypto backend.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecSignatureAlgorithm
+{
+    DsaSha1,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    DsaSha256,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    EcdsaSha1,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    EcdsaSha224,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    EcdsaSha256,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    EcdsaSha384,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    EcdsaSha512,
+    #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+    HmacMd5,
+    HmacRipemd160,
+    HmacSha1,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    HmacSha224,
+    HmacSha256,
+    HmacSha384,
+    HmacSha512,
+    #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+    RsaMd5,
+    #[cfg(not(feature = "nss"))]
+    RsaRipemd160,
+    RsaSha1,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    RsaSha224,
+    RsaSha256,
+    RsaSha384,
+    RsaSha512,
+}
+
+
+impl XmlSecSignatureAlgorithm
+{
+    /// Looks up the one corresponding to the given resource pointer, if it is one of the supported ones
+    pub fn from_method(id: bindings::xmlSecTransformId) -> Option<Self>
+    {
+        if id.is_null() {
+            return None;
+        }
+
+        Self::ALL.iter()
+            .find(|method| method.to_method() == id)
+            .copied()
+    }
+
+    /// Returns the resource pointer for the corresponding transform of the crypto backend
+    pub fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        self.transform_id()
+    }
+
+    /// Tells whether the crypto backend provides it at runtime, which varies with how xmlsec and the crypto library
+    /// were built. Initializes xmlsec if not yet done.
+    pub fn is_supported(&self) -> bool
+    {
+        transform_supported(self.to_method())
+    }
+
+    /// Lists the ones [supported][supported] at runtime.
+    ///
+    /// [supported]: #method.is_supported
+    pub fn supported_algorithms() -> Vec<Self>
+    {
+        Self::ALL.iter()
+            .filter(|method| method.is_supported())
+            .copied()
+            .collect()
+    }
+
+    /// Tells whether it is keyed-hash based, hence accepting `<dsig:HMACOutputLength/>`.
+    pub(crate) fn is_hmac(&self) -> bool
+    {
+        match self
+        {
+            #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+            Self::HmacMd5 => true,
+            #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+            Self::HmacSha224 => true,
+
+            Self::HmacRipemd160 | Self::HmacSha1 | Self::HmacSha256 | Self::HmacSha384 | Self::HmacSha512 => true,
+
+            _ => false,
+        }
+    }
+}
+
+
+#[allow(deprecated)]
+impl From<XmlSecSignatureAlgorithm> for XmlSecSignatureMethod
+{
+    fn from(method: XmlSecSignatureAlgorithm) -> Self
+    {
+        Self::from_algorithm(XmlSecAlgorithm::Signature(method))
+    }
+}
+
+
+#[allow(deprecated)]
+impl TryFrom<XmlSecSignatureMethod> for XmlSecSignatureAlgorithm
+{
+    type Error = XmlSecError;
+
+    /// Fails if the method is not a signature algorithm.
+    fn try_from(method: XmlSecSignatureMethod) -> Result<Self, Self::Error>
+    {
+        match method.algorithm()
+        {
+            XmlSecAlgorithm::Signature(method) => Ok(method),
+            _ => Err(format!("{:?} is not a signature algorithm", method).into()),
+        }
+    }
+}
+
+
+/// Digest methods of `<ds:Reference/>`, as specified by the XML standard. Which ones are available depends on the
+/// crypto backend.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecDigestAlgorithm
+{
+    #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+    Md5,
+    #[cfg(not(feature = "nss"))]
+    Ripemd160,
+    Sha1,
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+
+impl XmlSecDigestAlgorithm
+{
+    /// Looks up the one corresponding to the given resource pointer, if it is one of the supported ones
+    pub fn from_method(id: bindings::xmlSecTransformId) -> Option<Self>
+    {
+        if id.is_null() {
+            return None;
+        }
+
+        Self::ALL.iter()
+            .find(|method| method.to_method() == id)
+            .copied()
+    }
+
+    /// Returns the resource pointer for the corresponding transform of the crypto backend
+    pub fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        self.transform_id()
+    }
+
+    /// Tells whether the crypto backend provides it at runtime, which varies with how xmlsec and the crypto library
+    /// were built. Initializes xmlsec if not yet done.
+    pub fn is_supported(&self) -> bool
+    {
+        transform_supported(self.to_method())
+    }
+
+    /// Lists the ones [supported][supported] at runtime.
+    ///
+    /// [supported]: #method.is_supported
+    pub fn supported_algorithms() -> Vec<Self>
+    {
+        Self::ALL.iter()
+            .filter(|method| method.is_supported())
+            .copied()
+            .collect()
+    }
+}
+
+
+#[allow(deprecated)]
+impl From<XmlSecDigestAlgorithm> for XmlSecSignatureMethod
+{
+    fn from(method: XmlSecDigestAlgorithm) -> Self
+    {
+        Self::from_algorithm(XmlSecAlgorithm::Digest(method))
+    }
+}
+
+
+#[allow(deprecated)]
+impl TryFrom<XmlSecSignatureMethod> for XmlSecDigestAlgorithm
+{
+    type Error = XmlSecError;
+
+    /// Fails if the method is not a digest algorithm.
+    fn try_from(method: XmlSecSignatureMethod) -> Result<Self, Self::Error>
+    {
+        match method.algorithm()
+        {
+            XmlSecAlgorithm::Digest(method) => Ok(method),
+            _ => Err(format!("{:?} is not a digest algorithm", method).into()),
+        }
+    }
+}
+
+
+/// Block ciphers encrypting the data of `<enc:EncryptedData/>`, as specified by the XML standard.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecBlockCipher
+{
+    Aes128Cbc,
+    Aes192Cbc,
+    Aes256Cbc,
+    Des3Cbc,
+}
+
+
+impl XmlSecBlockCipher
+{
+    /// Looks up the one corresponding to the given resource pointer, if it is one of the supported ones
+    pub fn from_method(id: bindings::xmlSecTransformId) -> Option<Self>
+    {
+        if id.is_null() {
+            return None;
+        }
+
+        Self::ALL.iter()
+            .find(|method| method.to_method() == id)
+            .copied()
+    }
+
+    /// Returns the resource pointer for the corresponding transform of the crypto backend
+    pub fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        self.transform_id()
+    }
+
+    /// Tells whether the crypto backend provides it at runtime, which varies with how xmlsec and the crypto library
+    /// were built. Initializes xmlsec if not yet done.
+    pub fn is_supported(&self) -> bool
+    {
+        transform_supported(self.to_method())
+    }
+
+    /// Lists the ones [supported][supported] at runtime.
+    ///
+    /// [supported]: #method.is_supported
+    pub fn supported_algorithms() -> Vec<Self>
+    {
+        Self::ALL.iter()
+            .filter(|method| method.is_supported())
+            .copied()
+            .collect()
+    }
+}
+
+
+#[allow(deprecated)]
+impl From<XmlSecBlockCipher> for XmlSecSignatureMethod
+{
+    fn from(method: XmlSecBlockCipher) -> Self
+    {
+        Self::from_algorithm(XmlSecAlgorithm::BlockCipher(method))
+    }
+}
+
+
+#[allow(deprecated)]
+impl TryFrom<XmlSecSignatureMethod> for XmlSecBlockCipher
+{
+    type Error = XmlSecError;
+
+    /// Fails if the method is not a block cipher.
+    fn try_from(method: XmlSecSignatureMethod) -> Result<Self, Self::Error>
+    {
+        match method.algorithm()
+        {
+            XmlSecAlgorithm::BlockCipher(method) => Ok(method),
+            _ => Err(format!("{:?} is not a block cipher", method).into()),
+        }
+    }
+}
+
+
+/// Key transport and key wrap methods encrypting the data key within `<enc:EncryptedKey/>`, as specified by the XML
+/// standard. Which ones are available depends on the crypto backend.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecKeyTransport
+{
+    #[cfg(not(any(feature = "gnutls", feature = "gcrypt")))]
+    RsaPkcs1,
+    #[cfg(feature = "openssl")]
+    RsaOaep,
+    KWAes128,
+    KWAes192,
+    KWAes256,
+    KWDes3,
+}
+
+
+impl XmlSecKeyTransport
+{
+    /// Looks up the one corresponding to the given resource pointer, if it is one of the supported ones
+    pub fn from_method(id: bindings::xmlSecTransformId) -> Option<Self>
+    {
+        if id.is_null() {
+            return None;
+        }
+
+        Self::ALL.iter()
+            .find(|method| method.to_method() == id)
+            .copied()
+    }
+
+    /// Returns the resource pointer for the corresponding transform of the crypto backend
+    pub fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        self.transform_id()
+    }
+
+    /// Tells whether the crypto backend provides it at runtime, which varies with how xmlsec and the crypto library
+    /// were built. Initializes xmlsec if not yet done.
+    pub fn is_supported(&self) -> bool
+    {
+        transform_supported(self.to_method())
+    }
+
+    /// Lists the ones [supported][supported] at runtime.
+    ///
+    /// [supported]: #method.is_supported
+    pub fn supported_algorithms() -> Vec<Self>
+    {
+        Self::ALL.iter()
+            .filter(|method| method.is_supported())
+            .copied()
+            .collect()
+    }
+}
+
+
+#[allow(deprecated)]
+impl From<XmlSecKeyTransport> for XmlSecSignatureMethod
+{
+    fn from(method: XmlSecKeyTransport) -> Self
+    {
+        Self::from_algorithm(XmlSecAlgorithm::KeyTransport(method))
+    }
+}
+
+
+#[allow(deprecated)]
+impl TryFrom<XmlSecSignatureMethod> for XmlSecKeyTransport
+{
+    type Error = XmlSecError;
+
+    /// Fails if the method is not a key transport or key wrap method.
+    fn try_from(method: XmlSecSignatureMethod) -> Result<Self, Self::Error>
+    {
+        match method.algorithm()
+        {
+            XmlSecAlgorithm::KeyTransport(method) => Ok(method),
+            _ => Err(format!("{:?} is not a key transport or key wrap method", method).into()),
+        }
+    }
+}
+
+
+/// Typed algorithm a method of the deprecated [`XmlSecSignatureMethod`] stands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum XmlSecAlgorithm
+{
+    Signature(XmlSecSignatureAlgorithm),
+    Digest(XmlSecDigestAlgorithm),
+    BlockCipher(XmlSecBlockCipher),
+    KeyTransport(XmlSecKeyTransport),
+}
+
+
+/// Methods able to encrypt the data of `<enc:EncryptedData/>`: block ciphers, as well as key transports for data small
+/// enough, typically a key.
+pub trait XmlSecEncryptionMethod
+{
+    /// Returns the resource pointer for the corresponding transform of the crypto backend
+    fn to_method(&self) -> bindings::xmlSecTransformId;
+}
+
+
+impl XmlSecEncryptionMethod for XmlSecBlockCipher
+{
+    fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        XmlSecBlockCipher::to_method(self)
+    }
+}
+
+
+impl XmlSecEncryptionMethod for XmlSecKeyTransport
+{
+    fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        XmlSecKeyTransport::to_method(self)
+    }
+}
+
+
+#[allow(deprecated)]
+impl XmlSecEncryptionMethod for XmlSecSignatureMethod
+{
+    fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        XmlSecSignatureMethod::to_method(self)
+    }
+}
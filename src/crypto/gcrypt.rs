@@ -0,0 +1,74 @@
+//!
+//! Wrapping for GCrypt Crypto Backend Implementation for XmlSec Crypto Interface
+//!
+use crate::bindings;
+
+use std::ffi::CStr;
+use std::ptr::null;
+
+/// Name under which xmlsec knows this backend.
+pub(crate) const NAME: &str = "gcrypt";
+
+pub(crate) use bindings::xmlSecGCryptInit     as init;
+pub(crate) use bindings::xmlSecGCryptShutdown as shutdown;
+
+pub(crate) use bindings::xmlSecGCryptAppInit                    as app_init;
+pub(crate) use bindings::xmlSecGCryptAppShutdown                as app_shutdown;
+pub(crate) use bindings::xmlSecGCryptAppKeyLoad                 as app_key_load;
+pub(crate) use bindings::xmlSecGCryptAppKeyLoadMemory           as app_key_load_memory;
+pub(crate) use bindings::xmlSecGCryptAppKeyCertLoad             as app_key_cert_load;
+pub(crate) use bindings::xmlSecGCryptAppKeyCertLoadMemory       as app_key_cert_load_memory;
+pub(crate) use bindings::xmlSecGCryptAppDefaultKeysMngrInit     as app_default_keys_mngr_init;
+pub(crate) use bindings::xmlSecGCryptAppDefaultKeysMngrAdoptKey as app_default_keys_mngr_adopt_key;
+pub(crate) use bindings::xmlSecGCryptAppKeysMngrCertLoad        as app_keys_mngr_cert_load;
+pub(crate) use bindings::xmlSecGCryptAppKeysMngrCertLoadMemory  as app_keys_mngr_cert_load_memory;
+
+pub(crate) use bindings::xmlSecGCryptKeyDataHmacGetKlass as key_data_hmac;
+pub(crate) use bindings::xmlSecGCryptKeyDataAesGetKlass  as key_data_aes;
+pub(crate) use bindings::xmlSecGCryptKeyDataDesGetKlass  as key_data_des;
+
+pub(crate) use bindings::xmlSecGCryptTransformDsaSha1GetKlass       as transform_dsa_sha1;
+pub(crate) use bindings::xmlSecGCryptTransformHmacMd5GetKlass       as transform_hmac_md5;
+pub(crate) use bindings::xmlSecGCryptTransformHmacRipemd160GetKlass as transform_hmac_ripemd160;
+pub(crate) use bindings::xmlSecGCryptTransformHmacSha1GetKlass      as transform_hmac_sha1;
+pub(crate) use bindings::xmlSecGCryptTransformHmacSha256GetKlass    as transform_hmac_sha256;
+pub(crate) use bindings::xmlSecGCryptTransformHmacSha384GetKlass    as transform_hmac_sha384;
+pub(crate) use bindings::xmlSecGCryptTransformHmacSha512GetKlass    as transform_hmac_sha512;
+pub(crate) use bindings::xmlSecGCryptTransformRsaMd5GetKlass        as transform_rsa_md5;
+pub(crate) use bindings::xmlSecGCryptTransformRsaRipemd160GetKlass  as transform_rsa_ripemd160;
+pub(crate) use bindings::xmlSecGCryptTransformRsaSha1GetKlass       as transform_rsa_sha1;
+pub(crate) use bindings::xmlSecGCryptTransformRsaSha256GetKlass     as transform_rsa_sha256;
+pub(crate) use bindings::xmlSecGCryptTransformRsaSha384GetKlass     as transform_rsa_sha384;
+pub(crate) use bindings::xmlSecGCryptTransformRsaSha512GetKlass     as transform_rsa_sha512;
+pub(crate) use bindings::xmlSecGCryptTransformMd5GetKlass           as transform_md5;
+pub(crate) use bindings::xmlSecGCryptTransformRipemd160GetKlass     as transform_ripemd160;
+pub(crate) use bindings::xmlSecGCryptTransformSha1GetKlass          as transform_sha1;
+pub(crate) use bindings::xmlSecGCryptTransformSha256GetKlass        as transform_sha256;
+pub(crate) use bindings::xmlSecGCryptTransformSha384GetKlass        as transform_sha384;
+pub(crate) use bindings::xmlSecGCryptTransformSha512GetKlass        as transform_sha512;
+pub(crate) use bindings::xmlSecGCryptTransformAes128CbcGetKlass     as transform_aes128_cbc;
+pub(crate) use bindings::xmlSecGCryptTransformAes192CbcGetKlass     as transform_aes192_cbc;
+pub(crate) use bindings::xmlSecGCryptTransformAes256CbcGetKlass     as transform_aes256_cbc;
+pub(crate) use bindings::xmlSecGCryptTransformDes3CbcGetKlass       as transform_des3_cbc;
+pub(crate) use bindings::xmlSecGCryptTransformKWAes128GetKlass      as transform_kw_aes128;
+pub(crate) use bindings::xmlSecGCryptTransformKWAes192GetKlass      as transform_kw_aes192;
+pub(crate) use bindings::xmlSecGCryptTransformKWAes256GetKlass      as transform_kw_aes256;
+pub(crate) use bindings::xmlSecGCryptTransformKWDes3GetKlass        as transform_kw_des3;
+
+#[allow(deprecated)]
+pub use super::gnutls_gcrypt::XmlSecSignatureMethod;
+pub(crate) use super::gnutls_gcrypt::key_cert_subject;
+pub(crate) use super::gnutls_gcrypt::key_cert_verified;
+
+
+/// Version of the libgcrypt library in use, as reported by it.
+pub(crate) fn crypto_version() -> Option<String>
+{
+    let version = unsafe { bindings::gcry_check_version(null()) };
+
+    if version.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(version) }.to_string_lossy().into_owned())
+    }
+}
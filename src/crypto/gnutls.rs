@@ -0,0 +1,74 @@
+//!
+//! Wrapping for GnuTLS Crypto Backend Implementation for XmlSec Crypto Interface
+//!
+use crate::bindings;
+
+use std::ffi::CStr;
+use std::ptr::null;
+
+/// Name under which xmlsec knows this backend.
+pub(crate) const NAME: &str = "gnutls";
+
+pub(crate) use bindings::xmlSecGnuTLSInit     as init;
+pub(crate) use bindings::xmlSecGnuTLSShutdown as shutdown;
+
+pub(crate) use bindings::xmlSecGnuTLSAppInit                    as app_init;
+pub(crate) use bindings::xmlSecGnuTLSAppShutdown                as app_shutdown;
+pub(crate) use bindings::xmlSecGnuTLSAppKeyLoad                 as app_key_load;
+pub(crate) use bindings::xmlSecGnuTLSAppKeyLoadMemory           as app_key_load_memory;
+pub(crate) use bindings::xmlSecGnuTLSAppKeyCertLoad             as app_key_cert_load;
+pub(crate) use bindings::xmlSecGnuTLSAppKeyCertLoadMemory       as app_key_cert_load_memory;
+pub(crate) use bindings::xmlSecGnuTLSAppDefaultKeysMngrInit     as app_default_keys_mngr_init;
+pub(crate) use bindings::xmlSecGnuTLSAppDefaultKeysMngrAdoptKey as app_default_keys_mngr_adopt_key;
+pub(crate) use bindings::xmlSecGnuTLSAppKeysMngrCertLoad        as app_keys_mngr_cert_load;
+pub(crate) use bindings::xmlSecGnuTLSAppKeysMngrCertLoadMemory  as app_keys_mngr_cert_load_memory;
+
+pub(crate) use bindings::xmlSecGnuTLSKeyDataHmacGetKlass as key_data_hmac;
+pub(crate) use bindings::xmlSecGnuTLSKeyDataAesGetKlass  as key_data_aes;
+pub(crate) use bindings::xmlSecGnuTLSKeyDataDesGetKlass  as key_data_des;
+
+pub(crate) use bindings::xmlSecGnuTLSTransformDsaSha1GetKlass       as transform_dsa_sha1;
+pub(crate) use bindings::xmlSecGnuTLSTransformHmacMd5GetKlass       as transform_hmac_md5;
+pub(crate) use bindings::xmlSecGnuTLSTransformHmacRipemd160GetKlass as transform_hmac_ripemd160;
+pub(crate) use bindings::xmlSecGnuTLSTransformHmacSha1GetKlass      as transform_hmac_sha1;
+pub(crate) use bindings::xmlSecGnuTLSTransformHmacSha256GetKlass    as transform_hmac_sha256;
+pub(crate) use bindings::xmlSecGnuTLSTransformHmacSha384GetKlass    as transform_hmac_sha384;
+pub(crate) use bindings::xmlSecGnuTLSTransformHmacSha512GetKlass    as transform_hmac_sha512;
+pub(crate) use bindings::xmlSecGnuTLSTransformRsaMd5GetKlass        as transform_rsa_md5;
+pub(crate) use bindings::xmlSecGnuTLSTransformRsaRipemd160GetKlass  as transform_rsa_ripemd160;
+pub(crate) use bindings::xmlSecGnuTLSTransformRsaSha1GetKlass       as transform_rsa_sha1;
+pub(crate) use bindings::xmlSecGnuTLSTransformRsaSha256GetKlass     as transform_rsa_sha256;
+pub(crate) use bindings::xmlSecGnuTLSTransformRsaSha384GetKlass     as transform_rsa_sha384;
+pub(crate) use bindings::xmlSecGnuTLSTransformRsaSha512GetKlass     as transform_rsa_sha512;
+pub(crate) use bindings::xmlSecGnuTLSTransformMd5GetKlass           as transform_md5;
+pub(crate) use bindings::xmlSecGnuTLSTransformRipemd160GetKlass     as transform_ripemd160;
+pub(crate) use bindings::xmlSecGnuTLSTransformSha1GetKlass          as transform_sha1;
+pub(crate) use bindings::xmlSecGnuTLSTransformSha256GetKlass        as transform_sha256;
+pub(crate) use bindings::xmlSecGnuTLSTransformSha384GetKlass        as transform_sha384;
+pub(crate) use bindings::xmlSecGnuTLSTransformSha512GetKlass        as transform_sha512;
+pub(crate) use bindings::xmlSecGnuTLSTransformAes128CbcGetKlass     as transform_aes128_cbc;
+pub(crate) use bindings::xmlSecGnuTLSTransformAes192CbcGetKlass     as transform_aes192_cbc;
+pub(crate) use bindings::xmlSecGnuTLSTransformAes256CbcGetKlass     as transform_aes256_cbc;
+pub(crate) use bindings::xmlSecGnuTLSTransformDes3CbcGetKlass       as transform_des3_cbc;
+pub(crate) use bindings::xmlSecGnuTLSTransformKWAes128GetKlass      as transform_kw_aes128;
+pub(crate) use bindings::xmlSecGnuTLSTransformKWAes192GetKlass      as transform_kw_aes192;
+pub(crate) use bindings::xmlSecGnuTLSTransformKWAes256GetKlass      as transform_kw_aes256;
+pub(crate) use bindings::xmlSecGnuTLSTransformKWDes3GetKlass        as transform_kw_des3;
+
+#[allow(deprecated)]
+pub use super::gnutls_gcrypt::XmlSecSignatureMethod;
+pub(crate) use super::gnutls_gcrypt::key_cert_subject;
+pub(crate) use super::gnutls_gcrypt::key_cert_verified;
+
+
+/// Version of the GnuTLS library in use, as reported by it.
+pub(crate) fn crypto_version() -> Option<String>
+{
+    let version = unsafe { bindings::gnutls_check_version(null()) };
+
+    if version.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(version) }.to_string_lossy().into_owned())
+    }
+}
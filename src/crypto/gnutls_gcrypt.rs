@@ -0,0 +1,253 @@
+//!
+//! Algorithm Mapping Shared by the GnuTLS and GCrypt Crypto Backends
+//!
+//! Both backends of xmlsec implement the same set of transforms, which they merely export under their own prefix. The
+//! one compiled in provides these under common names, through which the typed algorithms are mapped here.
+//!
+use crate::bindings;
+
+use crate::XmlSecKeysManager;
+use crate::XmlSecSignatureAlgorithm;
+use crate::XmlSecDigestAlgorithm;
+use crate::XmlSecBlockCipher;
+use crate::XmlSecKeyTransport;
+use crate::crypto::algorithms::XmlSecAlgorithm;
+use crate::crypto::backend;
+
+
+/// Supported digesting and signing methods as specified by the XML standard, along with ciphers and key transports.
+#[allow(missing_docs)]
+#[deprecated(note = "use XmlSecSignatureAlgorithm, XmlSecDigestAlgorithm, XmlSecBlockCipher or XmlSecKeyTransport")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecSignatureMethod
+{
+    Aes128Cbc,
+    Aes192Cbc,
+    Aes256Cbc,
+    KWAes128,
+    KWAes192,
+    KWAes256,
+    Des3Cbc,
+    KWDes3,
+    DsaSha1,
+    HmacMd5,
+    HmacRipemd160,
+    HmacSha1,
+    HmacSha256,
+    HmacSha384,
+    HmacSha512,
+    Md5,
+    Ripemd160,
+    RsaMd5,
+    RsaRipemd160,
+    RsaSha1,
+    RsaSha256,
+    RsaSha384,
+    RsaSha512,
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+
+#[allow(deprecated)]
+impl XmlSecSignatureMethod
+{
+    pub(crate) const ALL: [Self; 27] = [
+        Self::Aes128Cbc,
+        Self::Aes192Cbc,
+        Self::Aes256Cbc,
+        Self::KWAes128,
+        Self::KWAes192,
+        Self::KWAes256,
+        Self::Des3Cbc,
+        Self::KWDes3,
+        Self::DsaSha1,
+        Self::HmacMd5,
+        Self::HmacRipemd160,
+        Self::HmacSha1,
+        Self::HmacSha256,
+        Self::HmacSha384,
+        Self::HmacSha512,
+        Self::Md5,
+        Self::Ripemd160,
+        Self::RsaMd5,
+        Self::RsaRipemd160,
+        Self::RsaSha1,
+        Self::RsaSha256,
+        Self::RsaSha384,
+        Self::RsaSha512,
+        Self::Sha1,
+        Self::Sha256,
+        Self::Sha384,
+        Self::Sha512,
+    ];
+
+    /// Typed algorithm the method stands for
+    pub(crate) fn algorithm(&self) -> XmlSecAlgorithm
+    {
+        match self
+        {
+            Self::Aes128Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes128Cbc),
+            Self::Aes192Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes192Cbc),
+            Self::Aes256Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes256Cbc),
+            Self::KWAes128      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes128),
+            Self::KWAes192      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes192),
+            Self::KWAes256      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes256),
+            Self::Des3Cbc       => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Des3Cbc),
+            Self::KWDes3        => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWDes3),
+            Self::DsaSha1       => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::DsaSha1),
+            Self::HmacMd5       => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacMd5),
+            Self::HmacRipemd160 => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacRipemd160),
+            Self::HmacSha1      => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha1),
+            Self::HmacSha256    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha256),
+            Self::HmacSha384    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha384),
+            Self::HmacSha512    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha512),
+            Self::Md5           => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Md5),
+            Self::Ripemd160     => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Ripemd160),
+            Self::RsaMd5        => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaMd5),
+            Self::RsaRipemd160  => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaRipemd160),
+            Self::RsaSha1       => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha1),
+            Self::RsaSha256     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha256),
+            Self::RsaSha384     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha384),
+            Self::RsaSha512     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha512),
+            Self::Sha1          => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha1),
+            Self::Sha256        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha256),
+            Self::Sha384        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha384),
+            Self::Sha512        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha512),
+        }
+    }
+}
+
+
+impl XmlSecSignatureAlgorithm
+{
+    pub(crate) const ALL: [Self; 13] = [
+        Self::DsaSha1,
+        Self::HmacMd5,
+        Self::HmacRipemd160,
+        Self::HmacSha1,
+        Self::HmacSha256,
+        Self::HmacSha384,
+        Self::HmacSha512,
+        Self::RsaMd5,
+        Self::RsaRipemd160,
+        Self::RsaSha1,
+        Self::RsaSha256,
+        Self::RsaSha384,
+        Self::RsaSha512,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::DsaSha1       => unsafe { backend::transform_dsa_sha1() },
+            Self::HmacMd5       => unsafe { backend::transform_hmac_md5() },
+            Self::HmacRipemd160 => unsafe { backend::transform_hmac_ripemd160() },
+            Self::HmacSha1      => unsafe { backend::transform_hmac_sha1() },
+            Self::HmacSha256    => unsafe { backend::transform_hmac_sha256() },
+            Self::HmacSha384    => unsafe { backend::transform_hmac_sha384() },
+            Self::HmacSha512    => unsafe { backend::transform_hmac_sha512() },
+            Self::RsaMd5        => unsafe { backend::transform_rsa_md5() },
+            Self::RsaRipemd160  => unsafe { backend::transform_rsa_ripemd160() },
+            Self::RsaSha1       => unsafe { backend::transform_rsa_sha1() },
+            Self::RsaSha256     => unsafe { backend::transform_rsa_sha256() },
+            Self::RsaSha384     => unsafe { backend::transform_rsa_sha384() },
+            Self::RsaSha512     => unsafe { backend::transform_rsa_sha512() },
+        }
+    }
+}
+
+
+impl XmlSecDigestAlgorithm
+{
+    pub(crate) const ALL: [Self; 6] = [
+        Self::Md5,
+        Self::Ripemd160,
+        Self::Sha1,
+        Self::Sha256,
+        Self::Sha384,
+        Self::Sha512,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::Md5       => unsafe { backend::transform_md5() },
+            Self::Ripemd160 => unsafe { backend::transform_ripemd160() },
+            Self::Sha1      => unsafe { backend::transform_sha1() },
+            Self::Sha256    => unsafe { backend::transform_sha256() },
+            Self::Sha384    => unsafe { backend::transform_sha384() },
+            Self::Sha512    => unsafe { backend::transform_sha512() },
+        }
+    }
+}
+
+
+impl XmlSecBlockCipher
+{
+    pub(crate) const ALL: [Self; 4] = [
+        Self::Aes128Cbc,
+        Self::Aes192Cbc,
+        Self::Aes256Cbc,
+        Self::Des3Cbc,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::Aes128Cbc => unsafe { backend::transform_aes128_cbc() },
+            Self::Aes192Cbc => unsafe { backend::transform_aes192_cbc() },
+            Self::Aes256Cbc => unsafe { backend::transform_aes256_cbc() },
+            Self::Des3Cbc   => unsafe { backend::transform_des3_cbc() },
+        }
+    }
+}
+
+
+impl XmlSecKeyTransport
+{
+    pub(crate) const ALL: [Self; 4] = [
+        Self::KWAes128,
+        Self::KWAes192,
+        Self::KWAes256,
+        Self::KWDes3,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::KWAes128 => unsafe { backend::transform_kw_aes128() },
+            Self::KWAes192 => unsafe { backend::transform_kw_aes192() },
+            Self::KWAes256 => unsafe { backend::transform_kw_aes256() },
+            Self::KWDes3   => unsafe { backend::transform_kw_des3() },
+        }
+    }
+}
+
+
+/// Certificates are only inspected with the OpenSSL backend.
+pub(crate) fn key_cert_subject(_key: *mut bindings::xmlSecKey) -> Option<String>
+{
+    None
+}
+
+
+/// Certificates are only inspected with the OpenSSL backend.
+pub(crate) fn key_cert_verified(
+    _mngr:       &XmlSecKeysManager,
+    _key:        *mut bindings::xmlSecKey,
+    _keyinfoctx: &mut bindings::xmlSecKeyInfoCtx
+) -> bool
+{
+    false
+}
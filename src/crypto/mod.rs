@@ -1,17 +1,129 @@
 //!
 //! Crypto Backend Wrappings
 //!
+//! Exactly one of the crypto backends of xmlsec is compiled in, as selected by the mutually exclusive `openssl`
+//! (default), `nss`, `gnutls` and `gcrypt` cargo features. Each of them maps the algorithms typed by kind in
+//! [`algorithms`] onto its own transforms, hence the algorithms available depend on the backend, and provides the glue
+//! through which the rest of the crate initializes it and loads keys, under the name `backend`. GnuTLS and GCrypt
+//! implement the same transforms, so they share their mapping in `gnutls_gcrypt` and only name their own transforms.
+//!
+//! Each backend also lists the methods of the deprecated [`XmlSecSignatureMethod`] mixing all of them, which delegate
+//! to the typed algorithms they stand for.
+//!
+use crate::bindings;
+
+use algorithms::XmlSecAlgorithm;
+
+pub(crate) mod algorithms;
+
+pub use algorithms::XmlSecSignatureAlgorithm;
+pub use algorithms::XmlSecDigestAlgorithm;
+pub use algorithms::XmlSecBlockCipher;
+pub use algorithms::XmlSecKeyTransport;
+pub use algorithms::XmlSecEncryptionMethod;
 
-// TODO do proper selection for the backend depending on env var set for xmlsec crypto
+#[cfg(feature = "nss")]
+pub(crate) mod nss;
+#[cfg(feature = "nss")]
+#[allow(deprecated)]
+pub use nss::XmlSecSignatureMethod;
+#[cfg(feature = "nss")]
+pub(crate) use nss as backend;
 
-// mod nss;
-// pub use nss::XmlSecSignatureMethod;
+#[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+pub(crate) mod gnutls_gcrypt;
 
-// mod gcrypt;
-// pub use gcrypt::XmlSecSignatureMethod;
+#[cfg(feature = "gcrypt")]
+pub(crate) mod gcrypt;
+#[cfg(feature = "gcrypt")]
+#[allow(deprecated)]
+pub use gcrypt::XmlSecSignatureMethod;
+#[cfg(feature = "gcrypt")]
+pub(crate) use gcrypt as backend;
 
-// mod gnutls;
-// pub use gnutls::XmlSecSignatureMethod;
+#[cfg(feature = "gnutls")]
+pub(crate) mod gnutls;
+#[cfg(feature = "gnutls")]
+#[allow(deprecated)]
+pub use gnutls::XmlSecSignatureMethod;
+#[cfg(feature = "gnutls")]
+pub(crate) use gnutls as backend;
 
-mod openssl;
+#[cfg(feature = "openssl")]
+pub(crate) mod openssl;
+#[cfg(feature = "openssl")]
+#[allow(deprecated)]
 pub use openssl::XmlSecSignatureMethod;
+#[cfg(feature = "openssl")]
+pub(crate) use openssl as backend;
+
+
+
+#[allow(deprecated)]
+impl XmlSecSignatureMethod
+{
+    /// Looks up the method corresponding to the given resource pointer, if it is one of the supported ones
+    pub fn from_method(id: bindings::xmlSecTransformId) -> Option<Self>
+    {
+        if id.is_null() {
+            return None;
+        }
+
+        Self::ALL.iter()
+            .find(|method| method.to_method() == id)
+            .copied()
+    }
+
+    /// Returns the resource pointer for the corresponding digesting/signing resource
+    pub fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        match self.algorithm()
+        {
+            XmlSecAlgorithm::Signature(algorithm)    => algorithm.to_method(),
+            XmlSecAlgorithm::Digest(algorithm)       => algorithm.to_method(),
+            XmlSecAlgorithm::BlockCipher(cipher)     => cipher.to_method(),
+            XmlSecAlgorithm::KeyTransport(transport) => transport.to_method(),
+        }
+    }
+
+    /// Tells whether the method is actually available at runtime, that is whether the crypto backend implements it
+    /// and registered it with xmlsec, which varies with how xmlsec and the crypto library were built. Initializes
+    /// xmlsec if not yet done.
+    pub fn is_supported(&self) -> bool
+    {
+        transform_supported(self.to_method())
+    }
+
+    /// Lists the methods known to this wrapper that are [supported][supported] at runtime.
+    ///
+    /// [supported]: #method.is_supported
+    pub fn supported_algorithms() -> Vec<Self>
+    {
+        Self::ALL.iter()
+            .filter(|method| method.is_supported())
+            .copied()
+            .collect()
+    }
+
+    /// The method standing for the given typed algorithm, every one of which the backend lists.
+    pub(crate) fn from_algorithm(algorithm: XmlSecAlgorithm) -> Self
+    {
+        Self::ALL.iter()
+            .find(|method| method.algorithm() == algorithm)
+            .copied()
+            .expect("Every algorithm of the backend should have its deprecated counterpart")
+    }
+}
+
+
+/// Tells whether the crypto backend registered the given transform with xmlsec.
+pub(crate) fn transform_supported(id: bindings::xmlSecTransformId) -> bool
+{
+    crate::xmlsec::guarantee_xmlsec_init();
+
+    if id.is_null() {
+        return false;
+    }
+
+    unsafe { bindings::xmlSecTransformIdListFind(bindings::xmlSecTransformIdsGet(), id) == 1 }
+}
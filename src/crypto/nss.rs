@@ -0,0 +1,310 @@
+//!
+//! Wrapping for NSS Crypto Backend Implementation for XmlSec Crypto Interface
+//!
+use crate::bindings;
+
+use crate::XmlSecKeysManager;
+use crate::XmlSecSignatureAlgorithm;
+use crate::XmlSecDigestAlgorithm;
+use crate::XmlSecBlockCipher;
+use crate::XmlSecKeyTransport;
+use crate::crypto::algorithms::XmlSecAlgorithm;
+
+use std::ffi::CStr;
+
+/// Name under which xmlsec knows this backend.
+pub(crate) const NAME: &str = "nss";
+
+pub(crate) use bindings::xmlSecNssInit     as init;
+pub(crate) use bindings::xmlSecNssShutdown as shutdown;
+
+pub(crate) use bindings::xmlSecNssAppInit                    as app_init;
+pub(crate) use bindings::xmlSecNssAppShutdown                as app_shutdown;
+pub(crate) use bindings::xmlSecNssAppKeyLoad                 as app_key_load;
+pub(crate) use bindings::xmlSecNssAppKeyLoadMemory           as app_key_load_memory;
+pub(crate) use bindings::xmlSecNssAppKeyCertLoad             as app_key_cert_load;
+pub(crate) use bindings::xmlSecNssAppKeyCertLoadMemory       as app_key_cert_load_memory;
+pub(crate) use bindings::xmlSecNssAppDefaultKeysMngrInit     as app_default_keys_mngr_init;
+pub(crate) use bindings::xmlSecNssAppDefaultKeysMngrAdoptKey as app_default_keys_mngr_adopt_key;
+pub(crate) use bindings::xmlSecNssAppKeysMngrCertLoad        as app_keys_mngr_cert_load;
+pub(crate) use bindings::xmlSecNssAppKeysMngrCertLoadMemory  as app_keys_mngr_cert_load_memory;
+
+pub(crate) use bindings::xmlSecNssKeyDataHmacGetKlass as key_data_hmac;
+pub(crate) use bindings::xmlSecNssKeyDataAesGetKlass  as key_data_aes;
+pub(crate) use bindings::xmlSecNssKeyDataDesGetKlass  as key_data_des;
+
+
+/// Supported digesting and signing methods as specified by the XML standard, along with ciphers and key transports.
+#[allow(missing_docs)]
+#[deprecated(note = "use XmlSecSignatureAlgorithm, XmlSecDigestAlgorithm, XmlSecBlockCipher or XmlSecKeyTransport")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecSignatureMethod
+{
+    Aes128Cbc,
+    Aes192Cbc,
+    Aes256Cbc,
+    KWAes128,
+    KWAes192,
+    KWAes256,
+    Des3Cbc,
+    KWDes3,
+    DsaSha1,
+    DsaSha256,
+    EcdsaSha1,
+    EcdsaSha224,
+    EcdsaSha256,
+    EcdsaSha384,
+    EcdsaSha512,
+    HmacRipemd160,
+    HmacSha1,
+    HmacSha224,
+    HmacSha256,
+    HmacSha384,
+    HmacSha512,
+    RsaSha1,
+    RsaSha224,
+    RsaSha256,
+    RsaSha384,
+    RsaSha512,
+    RsaPkcs1,
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+
+#[allow(deprecated)]
+impl XmlSecSignatureMethod
+{
+    pub(crate) const ALL: [Self; 32] = [
+        Self::Aes128Cbc,
+        Self::Aes192Cbc,
+        Self::Aes256Cbc,
+        Self::KWAes128,
+        Self::KWAes192,
+        Self::KWAes256,
+        Self::Des3Cbc,
+        Self::KWDes3,
+        Self::DsaSha1,
+        Self::DsaSha256,
+        Self::EcdsaSha1,
+        Self::EcdsaSha224,
+        Self::EcdsaSha256,
+        Self::EcdsaSha384,
+        Self::EcdsaSha512,
+        Self::HmacRipemd160,
+        Self::HmacSha1,
+        Self::HmacSha224,
+        Self::HmacSha256,
+        Self::HmacSha384,
+        Self::HmacSha512,
+        Self::RsaSha1,
+        Self::RsaSha224,
+        Self::RsaSha256,
+        Self::RsaSha384,
+        Self::RsaSha512,
+        Self::RsaPkcs1,
+        Self::Sha1,
+        Self::Sha224,
+        Self::Sha256,
+        Self::Sha384,
+        Self::Sha512,
+    ];
+
+    /// Typed algorithm the method stands for
+    pub(crate) fn algorithm(&self) -> XmlSecAlgorithm
+    {
+        match self
+        {
+            Self::Aes128Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes128Cbc),
+            Self::Aes192Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes192Cbc),
+            Self::Aes256Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes256Cbc),
+            Self::KWAes128      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes128),
+            Self::KWAes192      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes192),
+            Self::KWAes256      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes256),
+            Self::Des3Cbc       => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Des3Cbc),
+            Self::KWDes3        => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWDes3),
+            Self::DsaSha1       => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::DsaSha1),
+            Self::DsaSha256     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::DsaSha256),
+            Self::EcdsaSha1     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha1),
+            Self::EcdsaSha224   => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha224),
+            Self::EcdsaSha256   => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha256),
+            Self::EcdsaSha384   => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha384),
+            Self::EcdsaSha512   => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha512),
+            Self::HmacRipemd160 => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacRipemd160),
+            Self::HmacSha1      => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha1),
+            Self::HmacSha224    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha224),
+            Self::HmacSha256    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha256),
+            Self::HmacSha384    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha384),
+            Self::HmacSha512    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha512),
+            Self::RsaSha1       => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha1),
+            Self::RsaSha224     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha224),
+            Self::RsaSha256     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha256),
+            Self::RsaSha384     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha384),
+            Self::RsaSha512     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha512),
+            Self::RsaPkcs1      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::RsaPkcs1),
+            Self::Sha1          => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha1),
+            Self::Sha224        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha224),
+            Self::Sha256        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha256),
+            Self::Sha384        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha384),
+            Self::Sha512        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha512),
+        }
+    }
+}
+
+
+impl XmlSecSignatureAlgorithm
+{
+    pub(crate) const ALL: [Self; 18] = [
+        Self::DsaSha1,
+        Self::DsaSha256,
+        Self::EcdsaSha1,
+        Self::EcdsaSha224,
+        Self::EcdsaSha256,
+        Self::EcdsaSha384,
+        Self::EcdsaSha512,
+        Self::HmacRipemd160,
+        Self::HmacSha1,
+        Self::HmacSha224,
+        Self::HmacSha256,
+        Self::HmacSha384,
+        Self::HmacSha512,
+        Self::RsaSha1,
+        Self::RsaSha224,
+        Self::RsaSha256,
+        Self::RsaSha384,
+        Self::RsaSha512,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::DsaSha1       => unsafe { bindings::xmlSecNssTransformDsaSha1GetKlass() },
+            Self::DsaSha256     => unsafe { bindings::xmlSecNssTransformDsaSha256GetKlass() },
+            Self::EcdsaSha1     => unsafe { bindings::xmlSecNssTransformEcdsaSha1GetKlass() },
+            Self::EcdsaSha224   => unsafe { bindings::xmlSecNssTransformEcdsaSha224GetKlass() },
+            Self::EcdsaSha256   => unsafe { bindings::xmlSecNssTransformEcdsaSha256GetKlass() },
+            Self::EcdsaSha384   => unsafe { bindings::xmlSecNssTransformEcdsaSha384GetKlass() },
+            Self::EcdsaSha512   => unsafe { bindings::xmlSecNssTransformEcdsaSha512GetKlass() },
+            Self::HmacRipemd160 => unsafe { bindings::xmlSecNssTransformHmacRipemd160GetKlass() },
+            Self::HmacSha1      => unsafe { bindings::xmlSecNssTransformHmacSha1GetKlass() },
+            Self::HmacSha224    => unsafe { bindings::xmlSecNssTransformHmacSha224GetKlass() },
+            Self::HmacSha256    => unsafe { bindings::xmlSecNssTransformHmacSha256GetKlass() },
+            Self::HmacSha384    => unsafe { bindings::xmlSecNssTransformHmacSha384GetKlass() },
+            Self::HmacSha512    => unsafe { bindings::xmlSecNssTransformHmacSha512GetKlass() },
+            Self::RsaSha1       => unsafe { bindings::xmlSecNssTransformRsaSha1GetKlass() },
+            Self::RsaSha224     => unsafe { bindings::xmlSecNssTransformRsaSha224GetKlass() },
+            Self::RsaSha256     => unsafe { bindings::xmlSecNssTransformRsaSha256GetKlass() },
+            Self::RsaSha384     => unsafe { bindings::xmlSecNssTransformRsaSha384GetKlass() },
+            Self::RsaSha512     => unsafe { bindings::xmlSecNssTransformRsaSha512GetKlass() },
+        }
+    }
+}
+
+
+impl XmlSecDigestAlgorithm
+{
+    pub(crate) const ALL: [Self; 5] = [
+        Self::Sha1,
+        Self::Sha224,
+        Self::Sha256,
+        Self::Sha384,
+        Self::Sha512,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::Sha1   => unsafe { bindings::xmlSecNssTransformSha1GetKlass() },
+            Self::Sha224 => unsafe { bindings::xmlSecNssTransformSha224GetKlass() },
+            Self::Sha256 => unsafe { bindings::xmlSecNssTransformSha256GetKlass() },
+            Self::Sha384 => unsafe { bindings::xmlSecNssTransformSha384GetKlass() },
+            Self::Sha512 => unsafe { bindings::xmlSecNssTransformSha512GetKlass() },
+        }
+    }
+}
+
+
+impl XmlSecBlockCipher
+{
+    pub(crate) const ALL: [Self; 4] = [
+        Self::Aes128Cbc,
+        Self::Aes192Cbc,
+        Self::Aes256Cbc,
+        Self::Des3Cbc,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::Aes128Cbc => unsafe { bindings::xmlSecNssTransformAes128CbcGetKlass() },
+            Self::Aes192Cbc => unsafe { bindings::xmlSecNssTransformAes192CbcGetKlass() },
+            Self::Aes256Cbc => unsafe { bindings::xmlSecNssTransformAes256CbcGetKlass() },
+            Self::Des3Cbc   => unsafe { bindings::xmlSecNssTransformDes3CbcGetKlass() },
+        }
+    }
+}
+
+
+impl XmlSecKeyTransport
+{
+    pub(crate) const ALL: [Self; 5] = [
+        Self::RsaPkcs1,
+        Self::KWAes128,
+        Self::KWAes192,
+        Self::KWAes256,
+        Self::KWDes3,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::RsaPkcs1 => unsafe { bindings::xmlSecNssTransformRsaPkcs1GetKlass() },
+            Self::KWAes128 => unsafe { bindings::xmlSecNssTransformKWAes128GetKlass() },
+            Self::KWAes192 => unsafe { bindings::xmlSecNssTransformKWAes192GetKlass() },
+            Self::KWAes256 => unsafe { bindings::xmlSecNssTransformKWAes256GetKlass() },
+            Self::KWDes3   => unsafe { bindings::xmlSecNssTransformKWDes3GetKlass() },
+        }
+    }
+}
+
+
+/// Version of the NSS library in use, as reported by it.
+pub(crate) fn crypto_version() -> Option<String>
+{
+    let version = unsafe { bindings::NSS_GetVersion() };
+
+    if version.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(version) }.to_string_lossy().into_owned())
+    }
+}
+
+
+/// Certificates are only inspected with the OpenSSL backend.
+pub(crate) fn key_cert_subject(_key: *mut bindings::xmlSecKey) -> Option<String>
+{
+    None
+}
+
+
+/// Certificates are only inspected with the OpenSSL backend.
+pub(crate) fn key_cert_verified(
+    _mngr:       &XmlSecKeysManager,
+    _key:        *mut bindings::xmlSecKey,
+    _keyinfoctx: &mut bindings::xmlSecKeyInfoCtx
+) -> bool
+{
+    false
+}
@@ -3,9 +3,44 @@
 //!
 use crate::bindings;
 
+use crate::XmlSecKeysManager;
+use crate::XmlSecSignatureAlgorithm;
+use crate::XmlSecDigestAlgorithm;
+use crate::XmlSecBlockCipher;
+use crate::XmlSecKeyTransport;
+use crate::crypto::algorithms::XmlSecAlgorithm;
 
-/// Supported digesting and signing methods as specified by the XML standard.
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::os::raw::c_void;
+use std::ptr::null_mut;
+
+/// Name under which xmlsec knows this backend.
+pub(crate) const NAME: &str = "openssl";
+
+pub(crate) use bindings::xmlSecOpenSSLInit     as init;
+pub(crate) use bindings::xmlSecOpenSSLShutdown as shutdown;
+
+pub(crate) use bindings::xmlSecOpenSSLAppInit                    as app_init;
+pub(crate) use bindings::xmlSecOpenSSLAppShutdown                as app_shutdown;
+pub(crate) use bindings::xmlSecOpenSSLAppKeyLoad                 as app_key_load;
+pub(crate) use bindings::xmlSecOpenSSLAppKeyLoadMemory           as app_key_load_memory;
+pub(crate) use bindings::xmlSecOpenSSLAppKeyCertLoad             as app_key_cert_load;
+pub(crate) use bindings::xmlSecOpenSSLAppKeyCertLoadMemory       as app_key_cert_load_memory;
+pub(crate) use bindings::xmlSecOpenSSLAppDefaultKeysMngrInit     as app_default_keys_mngr_init;
+pub(crate) use bindings::xmlSecOpenSSLAppDefaultKeysMngrAdoptKey as app_default_keys_mngr_adopt_key;
+pub(crate) use bindings::xmlSecOpenSSLAppKeysMngrCertLoad        as app_keys_mngr_cert_load;
+pub(crate) use bindings::xmlSecOpenSSLAppKeysMngrCertLoadMemory  as app_keys_mngr_cert_load_memory;
+
+pub(crate) use bindings::xmlSecOpenSSLKeyDataHmacGetKlass as key_data_hmac;
+pub(crate) use bindings::xmlSecOpenSSLKeyDataAesGetKlass  as key_data_aes;
+pub(crate) use bindings::xmlSecOpenSSLKeyDataDesGetKlass  as key_data_des;
+
+
+/// Supported digesting and signing methods as specified by the XML standard, along with ciphers and key transports.
 #[allow(missing_docs)]
+#[deprecated(note = "use XmlSecSignatureAlgorithm, XmlSecDigestAlgorithm, XmlSecBlockCipher or XmlSecKeyTransport")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum XmlSecSignatureMethod
 {
     Aes128Cbc,
@@ -46,21 +81,121 @@ pub enum XmlSecSignatureMethod
 }
 
 
+#[allow(deprecated)]
 impl XmlSecSignatureMethod
 {
-    /// Returns the resource pointer for the corresponding digesting/signing resource
-    pub fn to_method(&self) -> bindings::xmlSecTransformId
+    pub(crate) const ALL: [Self; 35] = [
+        Self::Aes128Cbc,
+        Self::Aes192Cbc,
+        Self::Aes256Cbc,
+        Self::KWAes128,
+        Self::KWAes192,
+        Self::KWAes256,
+        Self::Des3Cbc,
+        Self::KWDes3,
+        Self::DsaSha1,
+        Self::DsaSha256,
+        Self::EcdsaSha1,
+        Self::EcdsaSha224,
+        Self::EcdsaSha256,
+        Self::EcdsaSha384,
+        Self::EcdsaSha512,
+        Self::HmacRipemd160,
+        Self::HmacSha1,
+        Self::HmacSha224,
+        Self::HmacSha256,
+        Self::HmacSha384,
+        Self::HmacSha512,
+        Self::Ripemd160,
+        Self::RsaRipemd160,
+        Self::RsaSha1,
+        Self::RsaSha224,
+        Self::RsaSha256,
+        Self::RsaSha384,
+        Self::RsaSha512,
+        Self::RsaPkcs1,
+        Self::RsaOaep,
+        Self::Sha1,
+        Self::Sha224,
+        Self::Sha256,
+        Self::Sha384,
+        Self::Sha512,
+    ];
+
+    /// Typed algorithm the method stands for
+    pub(crate) fn algorithm(&self) -> XmlSecAlgorithm
+    {
+        match self
+        {
+            Self::Aes128Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes128Cbc),
+            Self::Aes192Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes192Cbc),
+            Self::Aes256Cbc     => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Aes256Cbc),
+            Self::KWAes128      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes128),
+            Self::KWAes192      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes192),
+            Self::KWAes256      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWAes256),
+            Self::Des3Cbc       => XmlSecAlgorithm::BlockCipher(XmlSecBlockCipher::Des3Cbc),
+            Self::KWDes3        => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::KWDes3),
+            Self::DsaSha1       => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::DsaSha1),
+            Self::DsaSha256     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::DsaSha256),
+            Self::EcdsaSha1     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha1),
+            Self::EcdsaSha224   => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha224),
+            Self::EcdsaSha256   => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha256),
+            Self::EcdsaSha384   => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha384),
+            Self::EcdsaSha512   => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::EcdsaSha512),
+            Self::HmacRipemd160 => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacRipemd160),
+            Self::HmacSha1      => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha1),
+            Self::HmacSha224    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha224),
+            Self::HmacSha256    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha256),
+            Self::HmacSha384    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha384),
+            Self::HmacSha512    => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::HmacSha512),
+            Self::Ripemd160     => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Ripemd160),
+            Self::RsaRipemd160  => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaRipemd160),
+            Self::RsaSha1       => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha1),
+            Self::RsaSha224     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha224),
+            Self::RsaSha256     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha256),
+            Self::RsaSha384     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha384),
+            Self::RsaSha512     => XmlSecAlgorithm::Signature(XmlSecSignatureAlgorithm::RsaSha512),
+            Self::RsaPkcs1      => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::RsaPkcs1),
+            Self::RsaOaep       => XmlSecAlgorithm::KeyTransport(XmlSecKeyTransport::RsaOaep),
+            Self::Sha1          => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha1),
+            Self::Sha224        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha224),
+            Self::Sha256        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha256),
+            Self::Sha384        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha384),
+            Self::Sha512        => XmlSecAlgorithm::Digest(XmlSecDigestAlgorithm::Sha512),
+        }
+    }
+}
+
+
+impl XmlSecSignatureAlgorithm
+{
+    pub(crate) const ALL: [Self; 19] = [
+        Self::DsaSha1,
+        Self::DsaSha256,
+        Self::EcdsaSha1,
+        Self::EcdsaSha224,
+        Self::EcdsaSha256,
+        Self::EcdsaSha384,
+        Self::EcdsaSha512,
+        Self::HmacRipemd160,
+        Self::HmacSha1,
+        Self::HmacSha224,
+        Self::HmacSha256,
+        Self::HmacSha384,
+        Self::HmacSha512,
+        Self::RsaRipemd160,
+        Self::RsaSha1,
+        Self::RsaSha224,
+        Self::RsaSha256,
+        Self::RsaSha384,
+        Self::RsaSha512,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
     {
         match self
         {
-            Self::Aes128Cbc     => unsafe { bindings::xmlSecOpenSSLTransformAes128CbcGetKlass() },
-            Self::Aes192Cbc     => unsafe { bindings::xmlSecOpenSSLTransformAes192CbcGetKlass() },
-            Self::Aes256Cbc     => unsafe { bindings::xmlSecOpenSSLTransformAes256CbcGetKlass() },
-            Self::KWAes128      => unsafe { bindings::xmlSecOpenSSLTransformKWAes128GetKlass() },
-            Self::KWAes192      => unsafe { bindings::xmlSecOpenSSLTransformKWAes192GetKlass() },
-            Self::KWAes256      => unsafe { bindings::xmlSecOpenSSLTransformKWAes256GetKlass() },
-            Self::Des3Cbc       => unsafe { bindings::xmlSecOpenSSLTransformDes3CbcGetKlass() },
-            Self::KWDes3        => unsafe { bindings::xmlSecOpenSSLTransformKWDes3GetKlass() },
             Self::DsaSha1       => unsafe { bindings::xmlSecOpenSSLTransformDsaSha1GetKlass() },
             Self::DsaSha256     => unsafe { bindings::xmlSecOpenSSLTransformDsaSha256GetKlass() },
             Self::EcdsaSha1     => unsafe { bindings::xmlSecOpenSSLTransformEcdsaSha1GetKlass() },
@@ -74,20 +209,184 @@ impl XmlSecSignatureMethod
             Self::HmacSha256    => unsafe { bindings::xmlSecOpenSSLTransformHmacSha256GetKlass() },
             Self::HmacSha384    => unsafe { bindings::xmlSecOpenSSLTransformHmacSha384GetKlass() },
             Self::HmacSha512    => unsafe { bindings::xmlSecOpenSSLTransformHmacSha512GetKlass() },
-            Self::Ripemd160     => unsafe { bindings::xmlSecOpenSSLTransformRipemd160GetKlass() },
             Self::RsaRipemd160  => unsafe { bindings::xmlSecOpenSSLTransformRsaRipemd160GetKlass() },
             Self::RsaSha1       => unsafe { bindings::xmlSecOpenSSLTransformRsaSha1GetKlass() },
             Self::RsaSha224     => unsafe { bindings::xmlSecOpenSSLTransformRsaSha224GetKlass() },
             Self::RsaSha256     => unsafe { bindings::xmlSecOpenSSLTransformRsaSha256GetKlass() },
             Self::RsaSha384     => unsafe { bindings::xmlSecOpenSSLTransformRsaSha384GetKlass() },
             Self::RsaSha512     => unsafe { bindings::xmlSecOpenSSLTransformRsaSha512GetKlass() },
-            Self::RsaPkcs1      => unsafe { bindings::xmlSecOpenSSLTransformRsaPkcs1GetKlass() },
-            Self::RsaOaep       => unsafe { bindings::xmlSecOpenSSLTransformRsaOaepGetKlass() },
-            Self::Sha1          => unsafe { bindings::xmlSecOpenSSLTransformSha1GetKlass() },
-            Self::Sha224        => unsafe { bindings::xmlSecOpenSSLTransformSha224GetKlass() },
-            Self::Sha256        => unsafe { bindings::xmlSecOpenSSLTransformSha256GetKlass() },
-            Self::Sha384        => unsafe { bindings::xmlSecOpenSSLTransformSha384GetKlass() },
-            Self::Sha512        => unsafe { bindings::xmlSecOpenSSLTransformSha512GetKlass() },
+        }
+    }
+}
+
+
+impl XmlSecDigestAlgorithm
+{
+    pub(crate) const ALL: [Self; 6] = [
+        Self::Ripemd160,
+        Self::Sha1,
+        Self::Sha224,
+        Self::Sha256,
+        Self::Sha384,
+        Self::Sha512,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::Ripemd160 => unsafe { bindings::xmlSecOpenSSLTransformRipemd160GetKlass() },
+            Self::Sha1      => unsafe { bindings::xmlSecOpenSSLTransformSha1GetKlass() },
+            Self::Sha224    => unsafe { bindings::xmlSecOpenSSLTransformSha224GetKlass() },
+            Self::Sha256    => unsafe { bindings::xmlSecOpenSSLTransformSha256GetKlass() },
+            Self::Sha384    => unsafe { bindings::xmlSecOpenSSLTransformSha384GetKlass() },
+            Self::Sha512    => unsafe { bindings::xmlSecOpenSSLTransformSha512GetKlass() },
+        }
+    }
+}
+
+
+impl XmlSecBlockCipher
+{
+    pub(crate) const ALL: [Self; 4] = [
+        Self::Aes128Cbc,
+        Self::Aes192Cbc,
+        Self::Aes256Cbc,
+        Self::Des3Cbc,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::Aes128Cbc => unsafe { bindings::xmlSecOpenSSLTransformAes128CbcGetKlass() },
+            Self::Aes192Cbc => unsafe { bindings::xmlSecOpenSSLTransformAes192CbcGetKlass() },
+            Self::Aes256Cbc => unsafe { bindings::xmlSecOpenSSLTransformAes256CbcGetKlass() },
+            Self::Des3Cbc   => unsafe { bindings::xmlSecOpenSSLTransformDes3CbcGetKlass() },
+        }
+    }
+}
+
+
+impl XmlSecKeyTransport
+{
+    pub(crate) const ALL: [Self; 6] = [
+        Self::RsaPkcs1,
+        Self::RsaOaep,
+        Self::KWAes128,
+        Self::KWAes192,
+        Self::KWAes256,
+        Self::KWDes3,
+    ];
+
+    /// Resource pointer of the corresponding transform of this backend
+    pub(crate) fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::RsaPkcs1 => unsafe { bindings::xmlSecOpenSSLTransformRsaPkcs1GetKlass() },
+            Self::RsaOaep  => unsafe { bindings::xmlSecOpenSSLTransformRsaOaepGetKlass() },
+            Self::KWAes128 => unsafe { bindings::xmlSecOpenSSLTransformKWAes128GetKlass() },
+            Self::KWAes192 => unsafe { bindings::xmlSecOpenSSLTransformKWAes192GetKlass() },
+            Self::KWAes256 => unsafe { bindings::xmlSecOpenSSLTransformKWAes256GetKlass() },
+            Self::KWDes3   => unsafe { bindings::xmlSecOpenSSLTransformKWDes3GetKlass() },
+        }
+    }
+}
+
+
+/// Version of the OpenSSL library in use, as reported by it.
+pub(crate) fn crypto_version() -> Option<String>
+{
+    let version = unsafe { bindings::OpenSSL_version(bindings::OPENSSL_VERSION as i32) };
+
+    if version.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(version) }.to_string_lossy().into_owned())
+    }
+}
+
+
+/// Subject of the certificate the key is bound to, if any.
+pub(crate) fn key_cert_subject(key: *mut bindings::xmlSecKey) -> Option<String>
+{
+    let x509 = key_x509_data(key)?;
+
+    let mut buff = [0 as c_char; 512];
+
+    let name = unsafe { bindings::X509_NAME_oneline(
+        bindings::X509_get_subject_name(bindings::xmlSecOpenSSLKeyDataX509GetKeyCert(x509)),
+        buff.as_mut_ptr(),
+        buff.len() as i32
+    ) };
+
+    if name.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned())
+    }
+}
+
+
+/// Tells whether the certificate the key is bound to chains up to one of the trusted certificates of the manager.
+pub(crate) fn key_cert_verified(
+    mngr:       &XmlSecKeysManager,
+    key:        *mut bindings::xmlSecKey,
+    keyinfoctx: &mut bindings::xmlSecKeyInfoCtx
+) -> bool
+{
+    let store = mngr.x509_store();
+
+    let x509 = match key_x509_data(key)
+    {
+        Some(x509) => x509,
+        None       => return false,
+    };
+
+    unsafe {
+        // rebuild the chain with the key certificate and the ones travelling along with it
+        let certs = bindings::OPENSSL_sk_new_null();
+
+        if certs.is_null() {
+            panic!("Failed to allocate certificates stack");
+        }
+
+        bindings::OPENSSL_sk_push(certs, bindings::xmlSecOpenSSLKeyDataX509GetKeyCert(x509) as *const c_void);
+
+        for pos in 0..bindings::xmlSecOpenSSLKeyDataX509GetCertsSize(x509) {
+            bindings::OPENSSL_sk_push(certs, bindings::xmlSecOpenSSLKeyDataX509GetCert(x509, pos) as *const c_void);
+        }
+
+        let verified = bindings::xmlSecOpenSSLX509StoreVerify(
+            store,
+            certs as *mut bindings::stack_st_X509,
+            null_mut(),
+            keyinfoctx
+        );
+
+        bindings::OPENSSL_sk_free(certs);
+
+        ! verified.is_null()
+    }
+}
+
+
+fn key_x509_data(key: *mut bindings::xmlSecKey) -> Option<bindings::xmlSecKeyDataPtr>
+{
+    if key.is_null() {
+        return None;
+    }
+
+    unsafe {
+        let x509 = bindings::xmlSecKeyGetData(key, bindings::xmlSecOpenSSLKeyDataX509GetKlass());
+
+        if x509.is_null() || bindings::xmlSecOpenSSLKeyDataX509GetKeyCert(x509).is_null() {
+            None
+        } else {
+            Some(x509)
         }
     }
 }
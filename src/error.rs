@@ -1,6 +1,29 @@
 //!
 //! XmlSec High Level Error handling
 //!
+use crate::bindings;
+
+use crate::XmlSecErrorCallback;
+
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::sync::RwLock;
+
+
+/// Maximum amount of records kept per thread, so that noisy operations do not grow without bounds.
+const MAX_RECORDS: usize = 64;
+
+
+thread_local! {
+    static RECORDS: RefCell<Vec<XmlSecErrorRecord>> = const { RefCell::new(Vec::new()) };
+}
+
+
+/// Callback configured on explicit initialization, to which every record is handed over as well.
+static CALLBACK: RwLock<Option<XmlSecErrorCallback>> = RwLock::new(None);
 
 
 /// Wrapper project-wide Result typealias.
@@ -17,12 +40,48 @@ pub enum XmlSecError
     KeyNotLoaded,
     KeyLoadError,
     CertLoadError,
+    CrlLoadError,
+    KeysManagerError,
 
     RootNotFound,
     NodeNotFound,
+    NodeNotSigned,
 
     SigningError,
     VerifyError,
+    NotAllowed(String),
+
+    EncryptionError,
+    DecryptionError,
+
+    Library { kind: Box<XmlSecError>, details: Vec<XmlSecErrorRecord> },
+}
+
+
+/// Error as reported by the xmlsec library itself, collected while running an operation and attached to the
+/// [`XmlSecError::Library`][liberr] returned in case it fails. Records are listed in the order they were raised,
+/// hence the first one is usually the root cause while the rest trace it up the call chain.
+///
+/// [liberr]: enum.XmlSecError.html#variant.Library
+#[derive(Clone, Debug, PartialEq)]
+pub struct XmlSecErrorRecord
+{
+    /// xmlsec function raising the error.
+    pub func: String,
+    /// Source file of xmlsec in which the error was raised.
+    pub file: String,
+    /// Line within `file` at which the error was raised.
+    pub line: i32,
+    /// Name of the object (transform, key data, store...) the error relates to, if any.
+    pub object: Option<String>,
+    /// Name of the subject (usually a failed function) the error relates to, if any.
+    pub subject: Option<String>,
+    /// Reason code, as in `XMLSEC_ERRORS_R_*`.
+    pub reason: i32,
+    /// Human readable description of the reason code.
+    pub reason_msg: String,
+    /// Additional details of the error.
+    pub message: Option<String>,
 }
 
 
@@ -33,20 +92,85 @@ impl std::fmt::Display for XmlSecError
         match self
         {   Self::Str(reason) => write!(fmt, "{}", reason),
 
-            Self::KeyNotLoaded  => write!(fmt, "Key has not yet been loaded and is required"),
-            Self::KeyLoadError  => write!(fmt, "Failed to load key"),
-            Self::CertLoadError => write!(fmt, "Failed to load certificate"),
+            Self::KeyNotLoaded     => write!(fmt, "Key has not yet been loaded and is required"),
+            Self::KeyLoadError     => write!(fmt, "Failed to load key"),
+            Self::CertLoadError    => write!(fmt, "Failed to load certificate"),
+            Self::CrlLoadError     => write!(fmt, "Failed to load certificate revocation list"),
+            Self::KeysManagerError => write!(fmt, "Failed to setup or add keys to keys manager"),
+
+            Self::RootNotFound  => write!(fmt, "Failed to find document root"),
+            Self::NodeNotFound  => write!(fmt, "Failed to find node"),
+            Self::NodeNotSigned => write!(fmt, "Node is not covered by the signature"),
+
+            Self::SigningError     => write!(fmt, "An error has ocurred while attemting to sign document"),
+            Self::VerifyError      => write!(fmt, "Verification process failed"),
+            Self::NotAllowed(what) => write!(fmt, "Algorithm, transform or URI not allowed by policy: {}", what),
 
-            Self::RootNotFound => write!(fmt, "Failed to find document root"),
-            Self::NodeNotFound => write!(fmt, "Failed to find node"),
+            Self::EncryptionError => write!(fmt, "An error has ocurred while attempting to encrypt data"),
+            Self::DecryptionError => write!(fmt, "An error has ocurred while attempting to decrypt data"),
 
-            Self::SigningError => write!(fmt, "An error has ocurred while attemting to sign document"),
-            Self::VerifyError  => write!(fmt, "Verification process failed"),
+            Self::Library {kind, details} => match details.first()
+            {
+                Some(cause) => write!(fmt, "{}: {}", kind, cause),
+                None        => write!(fmt, "{}", kind),
+            },
         }
     }
 }
 
 
+impl XmlSecError
+{
+    /// Returns the error as it would be without the records of the xmlsec library, that is the one wrapped by a
+    /// [`XmlSecError::Library`][liberr] or `self` otherwise. Meant for matching on the kind of failure.
+    ///
+    /// [liberr]: enum.XmlSecError.html#variant.Library
+    pub fn kind(&self) -> &XmlSecError
+    {
+        match self
+        {
+            Self::Library {kind, ..} => kind,
+            _                        => self,
+        }
+    }
+
+    /// Returns the records reported by the xmlsec library for this error, if any.
+    pub fn details(&self) -> &[XmlSecErrorRecord]
+    {
+        match self
+        {
+            Self::Library {details, ..} => details,
+            _                           => &[],
+        }
+    }
+}
+
+
+impl std::fmt::Display for XmlSecErrorRecord
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(fmt, "{} (in {}", self.reason_msg, self.func)?;
+
+        if let Some(subject) = &self.subject {
+            write!(fmt, ", subject {}", subject)?;
+        }
+
+        if let Some(object) = &self.object {
+            write!(fmt, ", object {}", object)?;
+        }
+
+        write!(fmt, ")")?;
+
+        if let Some(message) = &self.message {
+            write!(fmt, ": {}", message)?;
+        }
+
+        Ok(())
+    }
+}
+
+
 impl std::error::Error for XmlSecError
 {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)>
@@ -72,3 +196,144 @@ impl From<String> for XmlSecError
         Self::Str(other)
     }
 }
+
+
+/// Conversions that cannot fail, so that the typed algorithms pass where the deprecated `XmlSecSignatureMethod`
+/// converts fallibly.
+impl From<Infallible> for XmlSecError
+{
+    fn from(never: Infallible) -> Self
+    {
+        match never {}
+    }
+}
+
+
+/// Forgets all records collected so far on the current thread. Called before running an operation, so that only the
+/// records pertaining to it get attached to its eventual error.
+pub(crate) fn clear_records()
+{
+    RECORDS.with(|records| records.borrow_mut().clear());
+}
+
+
+/// Wraps the given error into a [`XmlSecError::Library`][liberr] carrying the records collected on the current thread
+/// since the last call to `clear_records`. The error is returned as is when none were collected.
+///
+/// [liberr]: enum.XmlSecError.html#variant.Library
+pub(crate) fn library_error(error: XmlSecError) -> XmlSecError
+{
+    let details = RECORDS.with(|records| records.borrow_mut().split_off(0));
+
+    if details.is_empty() {
+        error
+    } else {
+        XmlSecError::Library {kind: Box::new(error), details}
+    }
+}
+
+
+/// Narrows down a [`XmlSecError::Library`][liberr] caused by something a context was told not to accept, such as a
+/// disabled transform or URI type, into a [`XmlSecError::NotAllowed`][notallowed] naming the culprit.
+///
+/// [liberr]: enum.XmlSecError.html#variant.Library
+/// [notallowed]: enum.XmlSecError.html#variant.NotAllowed
+pub(crate) fn policy_error(error: XmlSecError) -> XmlSecError
+{
+    let culprit = error.details()
+        .iter()
+        .find(|r| r.reason == bindings::XMLSEC_ERRORS_R_TRANSFORM_DISABLED as i32
+               || r.reason == bindings::XMLSEC_ERRORS_R_INVALID_URI_TYPE as i32);
+
+    match culprit
+    {
+        Some(record) => {
+            let what = record.message.as_deref()
+                .map(|m| m.trim_start_matches("href=").trim_start_matches("uri="))
+                .or(record.object.as_deref())
+                .unwrap_or("unknown");
+
+            XmlSecError::NotAllowed(what.to_owned())
+        },
+
+        None => error,
+    }
+}
+
+
+/// Sets or removes the callback every record collected gets handed over to.
+pub(crate) fn set_error_callback(callback: Option<XmlSecErrorCallback>)
+{
+    if let Ok(mut current) = CALLBACK.write() {
+        *current = callback;
+    }
+}
+
+
+/// Callback handed over to xmlsec through `xmlSecErrorsSetCallback`, collecting every error it raises on the current
+/// thread instead of printing it out.
+pub(crate) unsafe extern "C" fn collect_error(
+    file:    *const c_char,
+    line:    c_int,
+    func:    *const c_char,
+    object:  *const c_char,
+    subject: *const c_char,
+    reason:  c_int,
+    msg:     *const c_char,
+)
+{
+    let record = XmlSecErrorRecord {
+        func:       to_string(func).unwrap_or_else(|| "unknown".to_owned()),
+        file:       to_string(file).unwrap_or_else(|| "unknown".to_owned()),
+        line,
+        object:     to_string(object),
+        subject:    to_string(subject),
+        reason,
+        reason_msg: reason_msg(reason),
+        message:    to_string(msg).filter(|m| ! m.trim().is_empty() && m != "NULL"),
+    };
+
+    if let Ok(callback) = CALLBACK.read() && let Some(callback) = callback.as_ref() {
+        callback(&record);
+    }
+
+    // the thread may be tearing down its locals while xmlsec still reports errors
+    let _ = RECORDS.try_with(|records| {
+        let mut records = records.borrow_mut();
+
+        if records.len() < MAX_RECORDS {
+            records.push(record);
+        }
+    });
+}
+
+
+fn reason_msg(reason: c_int) -> String
+{
+    let mut pos = 0;
+
+    loop
+    {
+        let msg = unsafe { bindings::xmlSecErrorsGetMsg(pos) };
+
+        if msg.is_null() {
+            return format!("error {}", reason);
+        }
+
+        if unsafe { bindings::xmlSecErrorsGetCode(pos) } == reason {
+            return unsafe { CStr::from_ptr(msg) }.to_string_lossy().into_owned();
+        }
+
+        pos += 1;
+    }
+}
+
+
+fn to_string(ptr: *const c_char) -> Option<String>
+{
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+    }
+}
@@ -4,3 +4,7 @@
 mod xml;
 
 pub use xml::XmlSecDocumentExt;
+
+pub(crate) use xml::wrap_raw_node;
+pub(crate) use xml::wrap_unlinked_node;
+pub(crate) use xml::free_detached_nodes;
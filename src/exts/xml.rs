@@ -1,12 +1,16 @@
 //!
 //! XmlSec Extensions over LibXML2 Wrapper
 //!
+use crate::bindings as xmlsec_bindings;
+
 use crate::XmlSecResult;
 
+use crate::XmlNode;
 use crate::XmlDocument;
 use crate::XmlXPathContext;
 
 use libxml::bindings; // FIXME requires common bindings generation over libxml2
+use libxml::tree::NodeType as XmlNodeType;
 
 use std::ffi::CString;
 use std::os::raw::c_uchar;
@@ -74,3 +78,139 @@ impl XmlSecDocumentExt for XmlDocument
         Ok(())
     }
 }
+
+
+/// Looks up the [`XmlNode`][xmlnode] wrapping a raw node pointer, walking down from the top of the tree `anchor` is
+/// part of. This is needed since the wrapper is only ever handed out by libxml itself, which is not aware of nodes
+/// created or moved around by xmlsec.
+///
+/// [xmlnode]: http://kwarc.github.io/rust-libxml/libxml/tree/node/struct.Node.html
+pub(crate) fn wrap_raw_node(anchor: &XmlNode, node: *mut xmlsec_bindings::xmlNode) -> Option<XmlNode>
+{
+    if node.is_null() {
+        return None;
+    }
+
+    // ancestry of the raw node, from itself up to the topmost element
+    let mut ancestry = Vec::new();
+    let mut current  = node;
+
+    while ! current.is_null() && unsafe { (*current).type_ } != xmlsec_bindings::xmlElementType_XML_DOCUMENT_NODE
+    {
+        ancestry.push(current);
+        current = unsafe { (*current).parent };
+    }
+
+    // topmost node the anchor hangs from
+    let mut top = anchor.clone();
+
+    while let Some(parent) = top.get_parent()
+    {
+        if parent.get_type() == Some(XmlNodeType::DocumentNode) {
+            break;
+        }
+
+        top = parent;
+    }
+
+    let mut found = top;
+
+    // when anchored at the document itself, the walk down starts at its children
+    if found.get_type() != Some(XmlNodeType::DocumentNode)
+    {
+        match ancestry.pop()
+        {
+            Some(ptr) if ptr as usize == found.to_hashable() => {},
+            _ => return None,
+        }
+    }
+
+    while let Some(ptr) = ancestry.pop()
+    {
+        found = found.get_child_nodes()
+            .into_iter()
+            .find(|child| child.to_hashable() == ptr as usize)?;
+    }
+
+    Some(found)
+}
+
+
+/// Wraps a raw node that has been created for `doc` but not yet placed anywhere within it. If the document is still
+/// empty the node becomes its root, otherwise it is handed out unlinked, to be placed by whoever requested it.
+pub(crate) fn wrap_unlinked_node(doc: &XmlDocument, node: *mut xmlsec_bindings::xmlNode) -> Option<XmlNode>
+{
+    match doc.get_root_element()
+    {
+        Some(root) => {
+            // libxml only hands out wrappers for nodes it can reach, so briefly make it reachable
+            unsafe { xmlsec_bindings::xmlAddChild(root.node_ptr() as *mut xmlsec_bindings::xmlNode, node) };
+
+            let wrapped = wrap_raw_node(&root, node);
+
+            unsafe { xmlsec_bindings::xmlUnlinkNode(node) };
+
+            wrapped
+        }
+
+        None => {
+            unsafe { xmlsec_bindings::xmlDocSetRootElement(doc.doc_ptr() as *mut xmlsec_bindings::xmlDoc, node) };
+
+            doc.get_root_element()
+        }
+    }
+}
+
+
+/// Releases a list of sibling nodes xmlsec has detached from the document `anchor` is part of, along with their
+/// children. Wrappers handed out for any of them are left pointing at nothing, rather than at freed memory.
+pub(crate) fn free_detached_nodes(anchor: &XmlNode, nodes: *mut xmlsec_bindings::xmlNode)
+{
+    if nodes.is_null() {
+        return;
+    }
+
+    unsafe {
+        let doc  = (*nodes).doc;
+        let root = xmlsec_bindings::xmlDocGetRootElement(doc);
+
+        // libxml only neutralizes wrappers of nodes it can reach, so gather them under a scratch element in the tree
+        let name    = c"scratch".as_ptr() as *const c_uchar;
+        let scratch = xmlsec_bindings::xmlNewDocNode(doc, null_mut(), name, null_mut());
+
+        if scratch.is_null() {
+            return;
+        }
+
+        // linked by hand, as adding them one by one would merge adjacent text nodes
+        let mut last    = null_mut();
+        let mut current = nodes;
+
+        while ! current.is_null()
+        {
+            (*current).parent = scratch;
+
+            last    = current;
+            current = (*current).next;
+        }
+
+        (*scratch).children = nodes;
+        (*scratch).last     = last;
+
+        if root.is_null() {
+            xmlsec_bindings::xmlDocSetRootElement(doc, scratch);
+        } else {
+            xmlsec_bindings::xmlAddChild(root, scratch);
+        }
+
+        match wrap_raw_node(anchor, scratch)
+        {
+            Some(wrapped) => wrapped.free_subtree(),
+
+            None => {
+                xmlsec_bindings::xmlUnlinkNode(scratch);
+                xmlsec_bindings::xmlFreeNode(scratch);
+            }
+        }
+    }
+}
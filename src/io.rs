@@ -0,0 +1,129 @@
+//!
+//! Wrapper for XmlSec Input Callbacks
+//!
+use crate::bindings;
+
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::io::Cursor;
+use std::io::Read;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::panic::AssertUnwindSafe;
+use std::panic::catch_unwind;
+use std::rc::Rc;
+
+
+/// Resolver mapping the URI of a `<dsig:Reference/>` to the bytes it points at, or `None` if unknown.
+pub type XmlSecUriResolver = Rc<dyn Fn(&str) -> Option<Vec<u8>>>;
+
+
+thread_local! {
+    static ACTIVE: RefCell<Option<XmlSecUriResolver>> = const { RefCell::new(None) };
+}
+
+
+/// Makes a resolver the one serving all external URIs opened by xmlsec on the current thread, for as long as the
+/// guard lives. Any previously active resolver gets restored once dropped.
+pub(crate) struct ResolverGuard
+{
+    previous: Option<XmlSecUriResolver>,
+}
+
+
+impl ResolverGuard
+{
+    pub(crate) fn activate(resolver: Option<&XmlSecUriResolver>) -> Self
+    {
+        let previous = ACTIVE.with(|active| active.replace(resolver.cloned()));
+
+        Self {previous}
+    }
+}
+
+
+impl Drop for ResolverGuard
+{
+    fn drop(&mut self)
+    {
+        ACTIVE.with(|active| active.replace(self.previous.take()));
+    }
+}
+
+
+/// Registers our callbacks with xmlsec on top of its default ones, so that they get asked first.
+pub(crate) fn register_callbacks()
+{
+    let rc = unsafe { bindings::xmlSecIORegisterCallbacks(
+        Some(resolver_match),
+        Some(resolver_open),
+        Some(resolver_read),
+        Some(resolver_close),
+    ) };
+
+    if rc < 0 {
+        panic!("XmlSec failed to register input callbacks");
+    }
+}
+
+
+/// Claims every URI while a resolver is active, so that nothing ever reaches the network or filesystem behind its
+/// back, even when it cannot resolve a URI.
+unsafe extern "C" fn resolver_match(_uri: *const c_char) -> c_int
+{
+    ACTIVE.try_with(|active| active.borrow().is_some() as c_int)
+        .unwrap_or(0)
+}
+
+
+unsafe extern "C" fn resolver_open(uri: *const c_char) -> *mut c_void
+{
+    if uri.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    let uri = unsafe { CStr::from_ptr(uri) }.to_string_lossy();
+
+    let resolver = ACTIVE.try_with(|active| active.borrow().clone())
+        .ok()
+        .flatten();
+
+    // a panic must not unwind into xmlsec, it counts as not resolved instead
+    let data = catch_unwind(AssertUnwindSafe(|| resolver.and_then(|resolve| resolve(&uri))))
+        .ok()
+        .flatten();
+
+    match data
+    {
+        Some(data) => Box::into_raw(Box::new(Cursor::new(data))) as *mut c_void,
+        None       => std::ptr::null_mut(),
+    }
+}
+
+
+unsafe extern "C" fn resolver_read(ctx: *mut c_void, buffer: *mut c_char, len: c_int) -> c_int
+{
+    if ctx.is_null() || buffer.is_null() || len < 0 {
+        return -1;
+    }
+
+    let cursor = unsafe { &mut *(ctx as *mut Cursor<Vec<u8>>) };
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer as *mut u8, len as usize) };
+
+    match cursor.read(buffer)
+    {
+        Ok(read) => read as c_int,
+        Err(_)   => -1,
+    }
+}
+
+
+unsafe extern "C" fn resolver_close(ctx: *mut c_void) -> c_int
+{
+    if ! ctx.is_null() {
+        drop(unsafe { Box::from_raw(ctx as *mut Cursor<Vec<u8>>) });
+    }
+
+    0
+}
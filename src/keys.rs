@@ -5,6 +5,11 @@ use crate::bindings;
 
 use crate::XmlSecError;
 use crate::XmlSecResult;
+use crate::crypto::backend;
+use crate::error::clear_records;
+use crate::error::library_error;
+use crate::xmlsec::acquire_xmlsec;
+use crate::xmlsec::release_xmlsec;
 
 use std::ptr::null;
 use std::ptr::null_mut;
@@ -23,6 +28,9 @@ pub enum XmlSecKeyFormat
 {
     Unknown  = bindings::xmlSecKeyDataFormat_xmlSecKeyDataFormatUnknown,
     Binary   = bindings::xmlSecKeyDataFormat_xmlSecKeyDataFormatBinary,
+    /// PEM encoded key, only read by the OpenSSL backend. The others take DER instead: PKCS#8 for NSS, which also
+    /// expects certificates as [`Der`][Self::Der] rather than [`CertDer`][Self::CertDer], and PKCS#1 for GnuTLS and
+    /// GCrypt.
     Pem      = bindings::xmlSecKeyDataFormat_xmlSecKeyDataFormatPem,
     Der      = bindings::xmlSecKeyDataFormat_xmlSecKeyDataFormatDer,
     Pkcs8Pem = bindings::xmlSecKeyDataFormat_xmlSecKeyDataFormatPkcs8Pem,
@@ -33,6 +41,33 @@ pub enum XmlSecKeyFormat
 }
 
 
+/// Kind of a symmetric key, as loaded from its raw bytes with [`XmlSecKey::from_symmetric`][fromsym].
+///
+/// [fromsym]: struct.XmlSecKey.html#method.from_symmetric
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecSymmetricKeyKind
+{
+    Hmac,
+    Aes,
+    Des3,
+}
+
+
+impl XmlSecSymmetricKeyKind
+{
+    fn to_key_data(self) -> bindings::xmlSecKeyDataId
+    {
+        match self
+        {
+            Self::Hmac => unsafe { backend::key_data_hmac() },
+            Self::Aes  => unsafe { backend::key_data_aes() },
+            Self::Des3 => unsafe { backend::key_data_des() },
+        }
+    }
+}
+
+
 /// Key with which we sign/verify signatures or encrypt data. Used by [`XmlSecSignatureContext`][sigctx].
 ///
 /// [sigctx]: struct.XmlSecSignatureContext.html
@@ -57,8 +92,10 @@ impl XmlSecKey
         let cpasswd_ptr = cpasswd.map(|cstr| cstr.as_ptr())
             .unwrap_or(null());
 
+        clear_records();
+
         // Load key from file
-        let key = unsafe { bindings::xmlSecOpenSSLAppKeyLoad(
+        let key = unsafe { backend::app_key_load(
             cpath.as_ptr(),
             format as u32,
             cpasswd_ptr,
@@ -67,10 +104,10 @@ impl XmlSecKey
         ) };
 
         if key.is_null() {
-            return Err(XmlSecError::KeyLoadError);
+            return Err(library_error(XmlSecError::KeyLoadError));
         }
 
-        Ok(Self(key))
+        Ok(Self::wrap(key))
     }
 
     /// Load key from buffer in memory, specifying format and optionally the password required to decrypt/unlock.
@@ -84,8 +121,10 @@ impl XmlSecKey
         let cpasswd_ptr = cpasswd.map(|cstr| cstr.as_ptr())
             .unwrap_or(null());
 
+        clear_records();
+
         // Load key from buffer
-        let key = unsafe { bindings::xmlSecOpenSSLAppKeyLoadMemory(
+        let key = unsafe { backend::app_key_load_memory(
             buffer.as_ptr(),
             buffer.len() as u32,
             format as u32,
@@ -95,10 +134,33 @@ impl XmlSecKey
         ) };
 
         if key.is_null() {
-            return Err(XmlSecError::KeyLoadError);
+            return Err(library_error(XmlSecError::KeyLoadError));
+        }
+
+        Ok(Self::wrap(key))
+    }
+
+    /// Build a key for HMAC signature methods, such as `HmacSha256`, out of the shared secret.
+    pub fn from_hmac_secret(secret: &[u8]) -> XmlSecResult<Self>
+    {
+        Self::from_symmetric(secret, XmlSecSymmetricKeyKind::Hmac)
+    }
+
+    /// Build a symmetric key of the given kind out of its raw bytes, e.g. 16, 24 or 32 of them for AES and 24 for
+    /// 3DES. Whether their length fits the method is only checked once put to use.
+    pub fn from_symmetric(bytes: &[u8], kind: XmlSecSymmetricKeyKind) -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init();
+
+        clear_records();
+
+        let key = unsafe { bindings::xmlSecKeyReadMemory(kind.to_key_data(), bytes.as_ptr(), bytes.len() as u32) };
+
+        if key.is_null() {
+            return Err(library_error(XmlSecError::KeyLoadError));
         }
 
-        Ok(Self(key))
+        Ok(Self::wrap(key))
     }
 
     /// Load certificate into key by specifying path and ints format.
@@ -106,10 +168,12 @@ impl XmlSecKey
     {
         let cpath = CString::new(path).unwrap();
 
-        let rc = unsafe { bindings::xmlSecOpenSSLAppKeyCertLoad(self.0, cpath.as_ptr(), format as u32) };
+        clear_records();
+
+        let rc = unsafe { backend::app_key_cert_load(self.0, cpath.as_ptr(), format as u32) };
 
         if rc != 0 {
-            return Err(XmlSecError::CertLoadError);
+            return Err(library_error(XmlSecError::CertLoadError));
         }
 
         Ok(())
@@ -118,8 +182,10 @@ impl XmlSecKey
     /// Load certificate into key by specifying buffer to its contents.
     pub fn load_cert_from_memory(&self, buff: &[u8], format: XmlSecKeyFormat) -> XmlSecResult<()>
     {
+        clear_records();
+
         let rc = unsafe {
-            bindings::xmlSecOpenSSLAppKeyCertLoadMemory(
+            backend::app_key_cert_load_memory(
                 self.0,
                 buff.as_ptr(),
                 buff.len() as u32,
@@ -128,7 +194,7 @@ impl XmlSecKey
         };
 
         if rc != 0 {
-            return Err(XmlSecError::CertLoadError);
+            return Err(library_error(XmlSecError::CertLoadError));
         }
 
         Ok(())
@@ -164,7 +230,7 @@ impl XmlSecKey
     /// object.
     pub unsafe fn from_ptr(ptr: *mut bindings::xmlSecKey) -> Self
     {
-        Self(ptr)
+        Self::wrap(ptr)
     }
 
     /// # Safety
@@ -188,8 +254,17 @@ impl XmlSecKey
 
         std::mem::forget(key);
 
+        release_xmlsec();  // whoever takes it over keeps xmlsec alive on its own
+
         ptr
     }
+
+    fn wrap(ptr: *mut bindings::xmlSecKey) -> Self
+    {
+        acquire_xmlsec();
+
+        Self(ptr)
+    }
 }
 
 
@@ -211,7 +286,7 @@ impl Clone for XmlSecKey
     {
         let new = unsafe { bindings::xmlSecKeyDuplicate(self.0) };
 
-        Self(new)
+        Self::wrap(new)
     }
 }
 
@@ -221,5 +296,7 @@ impl Drop for XmlSecKey
     fn drop(&mut self)
     {
         unsafe { bindings::xmlSecKeyDestroy(self.0) };
+
+        release_xmlsec();
     }
 }
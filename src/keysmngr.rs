@@ -0,0 +1,309 @@
+//!
+//! Wrapper for XmlSec Keys Manager
+//!
+use crate::bindings;
+
+use crate::XmlSecKey;
+use crate::XmlSecKeyFormat;
+use crate::XmlSecError;
+use crate::XmlSecResult;
+use crate::crypto::backend;
+use crate::error::clear_records;
+use crate::error::library_error;
+use crate::xmlsec::acquire_xmlsec;
+use crate::xmlsec::release_xmlsec;
+
+use std::ffi::CString;
+use std::os::raw::c_uchar;
+#[cfg(feature = "openssl")]
+use std::os::raw::c_void;
+#[cfg(feature = "openssl")]
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+
+/// Keys manager holding a set of keys from which xmlsec resolves the one to use by looking at the `<ds:KeyInfo/>` of
+/// the signature or encrypted data being processed. Cloning it is cheap, as all clones share the same underlying
+/// manager, which is kept alive for as long as a clone or a context built upon it exists.
+#[derive(Clone, Debug)]
+pub struct XmlSecKeysManager
+{
+    mngr: Rc<KeysManagerPtr>,
+}
+
+
+#[derive(Debug)]
+struct KeysManagerPtr(*mut bindings::xmlSecKeysMngr);
+
+
+impl XmlSecKeysManager
+{
+    /// Builds an empty keys manager backed by the default simple keys store, ensuring xmlsec is initialized.
+    pub fn new() -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init();
+
+        let mngr = unsafe { bindings::xmlSecKeysMngrCreate() };
+
+        if mngr.is_null() {
+            return Err(XmlSecError::KeysManagerError);
+        }
+
+        acquire_xmlsec();
+
+        let mngr = KeysManagerPtr(mngr);  // from here on released on drop
+
+        let rc = unsafe { backend::app_default_keys_mngr_init(mngr.0) };
+
+        if rc < 0 {
+            return Err(XmlSecError::KeysManagerError);
+        }
+
+        Ok(Self {mngr: Rc::new(mngr)})
+    }
+
+    /// Adds a key to the manager, which takes over its lifetime. Keys are looked up by their name, so make sure to set
+    /// it with [`set_name`][setname] beforehand if they are meant to be found through `<ds:KeyName/>`.
+    ///
+    /// [setname]: struct.XmlSecKey.html#method.set_name
+    pub fn add_key(&self, key: XmlSecKey) -> XmlSecResult<()>
+    {
+        let keyptr = unsafe { XmlSecKey::leak(key) };
+
+        clear_records();
+
+        let rc = unsafe { backend::app_default_keys_mngr_adopt_key(self.mngr.0, keyptr) };
+
+        if rc < 0
+        {
+            unsafe { bindings::xmlSecKeyDestroy(keyptr) };
+
+            return Err(library_error(XmlSecError::KeysManagerError));
+        }
+
+        Ok(())
+    }
+
+    /// Looks up a key by its name, returning a copy of it if found.
+    pub fn find_key(&self, name: &str) -> XmlSecResult<Option<XmlSecKey>>
+    {
+        let cname = to_cstring(name, "Key name")?;
+
+        clear_records();
+
+        let keyinfoctx = unsafe { bindings::xmlSecKeyInfoCtxCreate(self.mngr.0) };
+
+        if keyinfoctx.is_null() {
+            return Err(library_error(XmlSecError::KeysManagerError));
+        }
+
+        let key = unsafe { bindings::xmlSecKeysMngrFindKey(
+            self.mngr.0,
+            cname.as_ptr() as *const c_uchar,
+            keyinfoctx
+        ) };
+
+        unsafe { bindings::xmlSecKeyInfoCtxDestroy(keyinfoctx) };
+
+        if key.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(unsafe { XmlSecKey::from_ptr(key) }))
+        }
+    }
+
+    /// Loads a certificate from file into the X509 store of the manager. Trusted certificates act as anchors against
+    /// which certificates found in `<ds:X509Data/>` get validated, while untrusted ones are only used as intermediates
+    /// to build the chain up to one of them.
+    pub fn load_cert_from_file(&self, path: &str, format: XmlSecKeyFormat, trusted: bool) -> XmlSecResult<()>
+    {
+        let cpath = to_cstring(path, "Path")?;
+
+        clear_records();
+
+        let rc = unsafe { backend::app_keys_mngr_cert_load(
+            self.mngr.0,
+            cpath.as_ptr(),
+            format as u32,
+            cert_type(trusted)
+        ) };
+
+        if rc != 0 {
+            return Err(library_error(XmlSecError::CertLoadError));
+        }
+
+        Ok(())
+    }
+
+    /// Loads a certificate from memory into the X509 store of the manager. See [`load_cert_from_file`][loadfile] for
+    /// the meaning of `trusted`.
+    ///
+    /// [loadfile]: #method.load_cert_from_file
+    pub fn load_cert_from_memory(&self, buff: &[u8], format: XmlSecKeyFormat, trusted: bool) -> XmlSecResult<()>
+    {
+        clear_records();
+
+        let rc = unsafe { backend::app_keys_mngr_cert_load_memory(
+            self.mngr.0,
+            buff.as_ptr(),
+            buff.len() as u32,
+            format as u32,
+            cert_type(trusted)
+        ) };
+
+        if rc != 0 {
+            return Err(library_error(XmlSecError::CertLoadError));
+        }
+
+        Ok(())
+    }
+
+    /// Adds a directory of trusted certificates to the X509 store of the manager. Certificates are looked up lazily
+    /// by their subject hash, hence the directory has to be laid out as done by `openssl rehash`. Only available with
+    /// the OpenSSL crypto backend.
+    #[cfg(feature = "openssl")]
+    pub fn add_trusted_certs_dir(&self, path: &str) -> XmlSecResult<()>
+    {
+        let cpath = to_cstring(path, "Path")?;
+
+        clear_records();
+
+        let rc = unsafe { bindings::xmlSecOpenSSLAppKeysMngrAddCertsPath(self.mngr.0, cpath.as_ptr()) };
+
+        if rc != 0 {
+            return Err(library_error(XmlSecError::CertLoadError));
+        }
+
+        Ok(())
+    }
+
+    /// Adds a file holding one or more PEM encoded trusted certificates to the X509 store of the manager. Only
+    /// available with the OpenSSL crypto backend.
+    #[cfg(feature = "openssl")]
+    pub fn add_trusted_certs_file(&self, path: &str) -> XmlSecResult<()>
+    {
+        let cpath = to_cstring(path, "Path")?;
+
+        clear_records();
+
+        let rc = unsafe { bindings::xmlSecOpenSSLAppKeysMngrAddCertsFile(self.mngr.0, cpath.as_ptr()) };
+
+        if rc != 0 {
+            return Err(library_error(XmlSecError::CertLoadError));
+        }
+
+        Ok(())
+    }
+
+    /// Loads a certificate revocation list from file into the X509 store of the manager. Only
+    /// [`XmlSecKeyFormat::Pem`][pem] and [`XmlSecKeyFormat::Der`][der] are supported, and only with the OpenSSL crypto
+    /// backend.
+    ///
+    /// [pem]: enum.XmlSecKeyFormat.html#variant.Pem
+    /// [der]: enum.XmlSecKeyFormat.html#variant.Der
+    #[cfg(feature = "openssl")]
+    pub fn load_crl_from_file(&self, path: &str, format: XmlSecKeyFormat) -> XmlSecResult<()>
+    {
+        let buff = std::fs::read(path)
+            .map_err(|_| XmlSecError::CrlLoadError)?;
+
+        self.load_crl_from_memory(&buff, format)
+    }
+
+    /// Loads a certificate revocation list from memory into the X509 store of the manager. See
+    /// [`load_crl_from_file`][loadfile] for the supported formats.
+    ///
+    /// [loadfile]: #method.load_crl_from_file
+    #[cfg(feature = "openssl")]
+    pub fn load_crl_from_memory(&self, buff: &[u8], format: XmlSecKeyFormat) -> XmlSecResult<()>
+    {
+        clear_records();
+
+        let bio = unsafe { bindings::BIO_new_mem_buf(buff.as_ptr() as *const c_void, buff.len() as i32) };
+
+        if bio.is_null() {
+            return Err(library_error(XmlSecError::CrlLoadError));
+        }
+
+        let crl = unsafe {
+            match format
+            {
+                XmlSecKeyFormat::Pem => bindings::PEM_read_bio_X509_CRL(bio, null_mut(), None, null_mut()),
+                XmlSecKeyFormat::Der => bindings::d2i_X509_CRL_bio(bio, null_mut()),
+
+                _ => null_mut(),
+            }
+        };
+
+        unsafe { bindings::BIO_free(bio) };
+
+        if crl.is_null() {
+            return Err(library_error(XmlSecError::CrlLoadError));
+        }
+
+        let store = self.x509_store();
+
+        let rc = unsafe { bindings::xmlSecOpenSSLX509StoreAdoptCrl(store, crl) };
+
+        if rc < 0
+        {
+            unsafe { bindings::X509_CRL_free(crl) };
+
+            return Err(library_error(XmlSecError::CrlLoadError));
+        }
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// Returns a raw pointer to the underlying xmlsec keys manager. Beware that it is still managed by this wrapping
+    /// object and will be deallocated once `self` and all its clones get dropped.
+    pub unsafe fn as_ptr(&self) -> *mut bindings::xmlSecKeysMngr
+    {
+        self.mngr.0
+    }
+
+    #[cfg(feature = "openssl")]
+    pub(crate) fn x509_store(&self) -> bindings::xmlSecKeyDataStorePtr
+    {
+        let store = unsafe { bindings::xmlSecKeysMngrGetDataStore(
+            self.mngr.0,
+            bindings::xmlSecOpenSSLX509StoreGetKlass()
+        ) };
+
+        if store.is_null() {
+            panic!("Failed to find X509 store of keys manager");
+        }
+
+        store
+    }
+}
+
+
+fn to_cstring(value: &str, what: &str) -> XmlSecResult<CString>
+{
+    CString::new(value)
+        .map_err(|_| XmlSecError::Str(format!("{} must not contain null bytes", what)))
+}
+
+
+fn cert_type(trusted: bool) -> bindings::xmlSecKeyDataType
+{
+    if trusted {
+        bindings::xmlSecKeyDataTypeTrusted
+    } else {
+        bindings::xmlSecKeyDataTypeNone
+    }
+}
+
+
+impl Drop for KeysManagerPtr
+{
+    fn drop(&mut self)
+    {
+        unsafe { bindings::xmlSecKeysMngrDestroy(self.0) };
+
+        release_xmlsec();
+    }
+}
@@ -21,10 +21,13 @@ pub use libxml::xpath::Context as XmlXPathContext;
 // internals
 mod exts;
 mod keys;
+mod keysmngr;
 mod error;
+mod io;
 mod crypto;
 mod xmlsec;
 mod xmldsig;
+mod xmlenc;
 mod templates;
 mod transforms;
 
@@ -35,29 +38,85 @@ pub use self::exts::XmlSecDocumentExt;
 
 pub use self::keys::XmlSecKey;
 pub use self::keys::XmlSecKeyFormat;
+pub use self::keys::XmlSecSymmetricKeyKind;
+
+pub use self::keysmngr::XmlSecKeysManager;
 
 pub use self::error::XmlSecError;
 pub use self::error::XmlSecResult;
+pub use self::error::XmlSecErrorRecord;
+
+pub use self::io::XmlSecUriResolver;
 
+pub use self::crypto::XmlSecSignatureAlgorithm;
+pub use self::crypto::XmlSecDigestAlgorithm;
+pub use self::crypto::XmlSecBlockCipher;
+pub use self::crypto::XmlSecKeyTransport;
+pub use self::crypto::XmlSecEncryptionMethod;
+
+#[allow(deprecated)]
 pub use self::crypto::XmlSecSignatureMethod;
 
+pub use self::xmlsec::XmlSec;
+pub use self::xmlsec::XmlSecConfig;
+pub use self::xmlsec::XmlSecGuard;
+pub use self::xmlsec::XmlSecVersions;
+pub use self::xmlsec::XmlSecErrorCallback;
+
 pub use self::xmldsig::XmlSecSignatureContext;
+pub use self::xmldsig::XmlSecX509Flag;
+pub use self::xmldsig::XmlSecVerificationReport;
+pub use self::xmldsig::XmlSecVerificationStatus;
+pub use self::xmldsig::XmlSecReferenceReport;
+pub use self::xmldsig::XmlSecSignatureReport;
+pub use self::xmldsig::XmlSecReferenceOrigin;
+pub use self::xmldsig::XmlSecReferenceUris;
+
+pub use self::xmlenc::XmlSecEncryptionContext;
+pub use self::xmlenc::XmlSecDecryptedData;
+pub use self::xmlenc::XmlSecEncryptionType;
 
 pub use self::templates::TemplateBuilder            as XmlSecTemplateBuilder;
 pub use self::templates::XmlDocumentTemplating      as XmlSecDocumentTemplating;
 pub use self::templates::XmlDocumentTemplateBuilder as XmlSecDocumentTemplateBuilder;
+pub use self::templates::ReferenceBuilder           as XmlSecReferenceBuilder;
+pub use self::templates::ObjectBuilder              as XmlSecObjectBuilder;
+pub use self::templates::SignaturePlacement         as XmlSecSignaturePlacement;
+pub use self::templates::KeyInfoBuilder             as XmlSecKeyInfoBuilder;
+
+pub use self::templates::EncryptionTemplateBuilder            as XmlSecEncryptionTemplateBuilder;
+pub use self::templates::XmlDocumentEncryptionTemplating      as XmlSecDocumentEncryptionTemplating;
+pub use self::templates::XmlDocumentEncryptionTemplateBuilder as XmlSecDocumentEncryptionTemplateBuilder;
 
 pub use self::transforms::XmlSecCanonicalizationMethod;
+pub use self::transforms::XmlSecTransform;
+pub use self::transforms::XmlSecXPath2Filter;
 
 // export preambles
 pub mod template {
-    //! Namespace for preamble pertaining all things signature template creation.
+    //! Namespace for preamble pertaining all things signature and encryption template creation.
 
     pub mod preamble {
-        //! Preamble of all things signature template creation.
+        //! Preamble of all things signature and encryption template creation.
         pub use crate::XmlSecTemplateBuilder;
         pub use crate::XmlSecDocumentTemplating;
+        pub use crate::XmlSecReferenceBuilder;
+        pub use crate::XmlSecObjectBuilder;
+        pub use crate::XmlSecSignaturePlacement;
+        pub use crate::XmlSecKeyInfoBuilder;
         pub use crate::XmlSecCanonicalizationMethod;
+        pub use crate::XmlSecTransform;
+        pub use crate::XmlSecXPath2Filter;
+        pub use crate::XmlSecSignatureAlgorithm;
+        pub use crate::XmlSecDigestAlgorithm;
+        pub use crate::XmlSecBlockCipher;
+        pub use crate::XmlSecKeyTransport;
+        pub use crate::XmlSecEncryptionMethod;
+        #[allow(deprecated)]
         pub use crate::XmlSecSignatureMethod;
+
+        pub use crate::XmlSecEncryptionTemplateBuilder;
+        pub use crate::XmlSecDocumentEncryptionTemplating;
+        pub use crate::XmlSecEncryptionType;
     }
 }
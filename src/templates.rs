@@ -3,17 +3,37 @@
 //!
 use crate::bindings;
 
+use crate::XmlNode;
 use crate::XmlDocument;
+use crate::XmlXPathContext;
 
 use crate::XmlSecCanonicalizationMethod;
-use crate::XmlSecSignatureMethod;
+use crate::XmlSecTransform;
+use crate::XmlSecSignatureAlgorithm;
+use crate::XmlSecDigestAlgorithm;
+use crate::XmlSecBlockCipher;
+use crate::XmlSecKeyTransport;
+use crate::XmlSecEncryptionMethod;
+use crate::XmlSecEncryptionType;
 
 use crate::XmlSecError;
 use crate::XmlSecResult;
 
+use crate::exts::wrap_raw_node;
+use crate::exts::wrap_unlinked_node;
+
+use libxml::tree::NodeType as XmlNodeType;
+
+use std::collections::hash_map::RandomState;
 use std::ffi::CString;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
 use std::os::raw::c_uchar;
+use std::os::raw::c_void;
 use std::ptr::null;
+use std::ptr::null_mut;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 
 /// Declaration of a template building API for other specific trait extensions
 /// on foreign XML objects.
@@ -24,19 +44,62 @@ pub trait TemplateBuilder
     /// [c14n]: ./transforms/enum.XmlSecCanonicalizationMethod.html
     fn canonicalization(self, c14n: XmlSecCanonicalizationMethod) -> Self;
 
-    /// Sets cryptographic signature method. See: [`XmlSecSignatureMethod`][sig].
+    /// Sets cryptographic signature method. See: [`XmlSecSignatureAlgorithm`][sig]. The deprecated
+    /// `XmlSecSignatureMethod` is still accepted, building the template failing if not a signature method.
     ///
-    /// [sig]: ./crypto/openssl/enum.XmlSecSignatureMethod.html
-    fn signature(self, sig: XmlSecSignatureMethod) -> Self;
+    /// [sig]: enum.XmlSecSignatureAlgorithm.html
+    fn signature<S>(self, sig: S) -> Self
+        where S: TryInto<XmlSecSignatureAlgorithm>, XmlSecError: From<S::Error>;
 
-    /// Sets cryptographic digest for `<dsig:Reference/>. See: [`XmlSecSignatureMethod`][sig].
+    /// Sets cryptographic digest for `<dsig:Reference/>. See: [`XmlSecDigestAlgorithm`][digest]. The deprecated
+    /// `XmlSecSignatureMethod` is still accepted, building the template failing if not a digest method.
     ///
-    /// [sig]: ./crypto/openssl/enum.XmlSecSignatureMethod.html
-    fn reference_signature(self, sig: XmlSecSignatureMethod) -> Self;
+    /// [digest]: enum.XmlSecDigestAlgorithm.html
+    fn reference_signature<D>(self, digest: D) -> Self
+        where D: TryInto<XmlSecDigestAlgorithm>, XmlSecError: From<D::Error>;
+
+    /// Truncates the output of HMAC signature methods to the given amount of bits, adding `<dsig:HMACOutputLength/>`
+    /// to the signature method. Verification refuses lengths below half the output of the hash, or 80 bits. Building
+    /// the template fails if the signature method is not an HMAC one.
+    fn hmac_output_length(self, bits: u32) -> Self;
 
     /// Sets signature subject node URI
     fn uri(self, uri: &str) -> Self;
 
+    /// Adds a `<dsig:Reference/>` described by a [`ReferenceBuilder`][refb]. May be called repeatedly, each call
+    /// adding another reference in order. Once any is added this way, the single reference otherwise set up from
+    /// [`uri`][uri] and [`reference_signature`][refsig] is not added anymore.
+    ///
+    /// [refb]: struct.ReferenceBuilder.html
+    /// [uri]: #tymethod.uri
+    /// [refsig]: #tymethod.reference_signature
+    fn reference(self, reference: ReferenceBuilder) -> Self;
+
+    /// Adds a `<dsig:Object/>` described by an [`ObjectBuilder`][objb] into the signature, along with a reference to
+    /// it by its `Id`, making it an enveloping signature. Like with [`reference`][reference], the single default
+    /// reference is not added anymore once any object is. Built into an empty document, the signature becomes a
+    /// standalone document of its own.
+    ///
+    /// [objb]: struct.ObjectBuilder.html
+    /// [reference]: #tymethod.reference
+    fn object(self, object: ObjectBuilder) -> Self;
+
+    /// Sets the `Id` attribute of `<dsig:Signature/>`
+    fn id(self, id: &str) -> Self;
+
+    /// Sets the `Id` attribute of `<dsig:SignedInfo/>`
+    fn signed_info_id(self, id: &str) -> Self;
+
+    /// Sets the `Id` attribute of `<dsig:KeyInfo/>`
+    fn keyinfo_id(self, id: &str) -> Self;
+
+    /// Generates a unique `Id` for each of `<dsig:Signature/>`, `<dsig:SignedInfo/>`, `<dsig:KeyInfo/>` and
+    /// `<dsig:Reference/>` not given one explicitly, e.g. `Signature-8f3a09c1d2e4b657`. These can be read back from
+    /// the node returned by [`done`][done].
+    ///
+    /// [done]: #tymethod.done
+    fn auto_ids(self, enable: bool) -> Self;
+
     /// the namespace prefix for the signature element (e.g. "dsig")
     fn ns_prefix(self, ns_prefix: &str) -> Self;
 
@@ -49,8 +112,50 @@ pub trait TemplateBuilder
     /// Adds <ds:X509Data> to key information node
     fn x509data(self, add: bool) -> Self;
 
-    /// Builds the actual template and returns
-    fn done(self) -> XmlSecResult<()>;
+    /// Adds the content described by a [`KeyInfoBuilder`][keyinfob] to the key information node, after whatever
+    /// [`keyname`][keyname], [`keyvalue`][keyvalue] and [`x509data`][x509data] added.
+    ///
+    /// [keyinfob]: struct.KeyInfoBuilder.html
+    /// [keyname]: #tymethod.keyname
+    /// [keyvalue]: #tymethod.keyvalue
+    /// [x509data]: #tymethod.x509data
+    fn keyinfo(self, keyinfo: KeyInfoBuilder) -> Self;
+
+    /// Sets where the signature gets placed within the document. See: [`SignaturePlacement`][placement].
+    ///
+    /// [placement]: enum.SignaturePlacement.html
+    fn placement(self, placement: SignaturePlacement) -> Self;
+
+    /// Builds the actual template and returns its `<dsig:Signature/>` node. Unless told otherwise with
+    /// [`placement`][placement], the signature is appended to the document root, or becomes the root itself if the
+    /// document is empty, as needed for detached signatures.
+    ///
+    /// [placement]: #tymethod.placement
+    fn done(self) -> XmlSecResult<XmlNode>;
+}
+
+/// Location of the `<dsig:Signature/>` built by a [`TemplateBuilder`][builder] within the document. Nodes given must
+/// belong to the document the template is built in.
+///
+/// [builder]: trait.TemplateBuilder.html
+pub enum SignaturePlacement
+{
+    /// Appended to the document root, or as the root itself in an empty document
+    Root,
+    /// Appended to the children of the given node
+    AppendTo(XmlNode),
+    /// Right before the given sibling, e.g. before `<Documento/>` on SII DTE
+    Before(XmlNode),
+    /// Right after the given sibling, e.g. after `<saml:Issuer/>` on SAML
+    After(XmlNode),
+    /// Appended to the children of the first node matched by an XPath expression
+    XPath
+    {
+        /// XPath expression, e.g. `//saml:Assertion`.
+        expression: String,
+        /// Namespaces used by the expression, as prefix and href pairs.
+        namespaces: Vec<(String, String)>,
+    },
 }
 
 /// Trait extension aimed at a concrete implementation for [`XmlDocument`][xmldoc]
@@ -75,15 +180,29 @@ struct TemplateOptions
 {
     c14n: XmlSecCanonicalizationMethod,
 
-    sig:    XmlSecSignatureMethod,
-    refsig: XmlSecSignatureMethod,
+    sig:     XmlSecSignatureAlgorithm,
+    refsig:  XmlSecDigestAlgorithm,
+    invalid: Option<XmlSecError>,
+
+    hmac_output_length: Option<u32>,
 
     ns_prefix: Option<String>,
     uri:       Option<String>,
 
+    references: Vec<ReferenceBuilder>,
+    objects:    Vec<ObjectBuilder>,
+
+    placement: SignaturePlacement,
+
+    id:             Option<String>,
+    signed_info_id: Option<String>,
+    keyinfo_id:     Option<String>,
+    auto_ids:       bool,
+
     keyname:  bool,
     keyvalue: bool,
     x509data: bool,
+    keyinfo:  Option<KeyInfoBuilder>,
 }
 
 impl Default for TemplateOptions
@@ -93,15 +212,29 @@ impl Default for TemplateOptions
         Self {
             c14n: XmlSecCanonicalizationMethod::ExclusiveC14N,
 
-            sig:    XmlSecSignatureMethod::RsaSha1,
-            refsig: XmlSecSignatureMethod::Sha1,
+            sig:     XmlSecSignatureAlgorithm::RsaSha1,
+            refsig:  XmlSecDigestAlgorithm::Sha1,
+            invalid: None,
+
+            hmac_output_length: None,
 
             uri:       None,
             ns_prefix: None,
 
+            references: Vec::new(),
+            objects:    Vec::new(),
+
+            placement: SignaturePlacement::Root,
+
+            id:             None,
+            signed_info_id: None,
+            keyinfo_id:     None,
+            auto_ids:       false,
+
             keyname:  false,
             keyvalue: false,
             x509data: false,
+            keyinfo:  None,
         }
     }
 }
@@ -124,15 +257,29 @@ impl<'d> TemplateBuilder for XmlDocumentTemplateBuilder<'d>
         self
     }
 
-    fn signature(mut self, sig: XmlSecSignatureMethod) -> Self
+    fn signature<S>(mut self, sig: S) -> Self
+        where S: TryInto<XmlSecSignatureAlgorithm>, XmlSecError: From<S::Error>
+    {
+        if let Some(sig) = checked_algorithm(sig, &mut self.options.invalid) {
+            self.options.sig = sig;
+        }
+
+        self
+    }
+
+    fn reference_signature<D>(mut self, digest: D) -> Self
+        where D: TryInto<XmlSecDigestAlgorithm>, XmlSecError: From<D::Error>
     {
-        self.options.sig = sig;
+        if let Some(digest) = checked_algorithm(digest, &mut self.options.invalid) {
+            self.options.refsig = digest;
+        }
+
         self
     }
 
-    fn reference_signature(mut self, sig: XmlSecSignatureMethod) -> Self
+    fn hmac_output_length(mut self, bits: u32) -> Self
     {
-        self.options.refsig = sig;
+        self.options.hmac_output_length = Some(bits);
         self
     }
 
@@ -142,6 +289,48 @@ impl<'d> TemplateBuilder for XmlDocumentTemplateBuilder<'d>
         self
     }
 
+    fn reference(mut self, reference: ReferenceBuilder) -> Self
+    {
+        self.options.references.push(reference);
+        self
+    }
+
+    fn object(mut self, object: ObjectBuilder) -> Self
+    {
+        self.options.objects.push(object);
+        self
+    }
+
+    fn placement(mut self, placement: SignaturePlacement) -> Self
+    {
+        self.options.placement = placement;
+        self
+    }
+
+    fn id(mut self, id: &str) -> Self
+    {
+        self.options.id = Some(id.to_owned());
+        self
+    }
+
+    fn signed_info_id(mut self, id: &str) -> Self
+    {
+        self.options.signed_info_id = Some(id.to_owned());
+        self
+    }
+
+    fn keyinfo_id(mut self, id: &str) -> Self
+    {
+        self.options.keyinfo_id = Some(id.to_owned());
+        self
+    }
+
+    fn auto_ids(mut self, enable: bool) -> Self
+    {
+        self.options.auto_ids = enable;
+        self
+    }
+
     fn ns_prefix(mut self, ns_prefix: &str) -> Self
     {
         self.options.ns_prefix = Some(ns_prefix.to_owned());
@@ -166,15 +355,26 @@ impl<'d> TemplateBuilder for XmlDocumentTemplateBuilder<'d>
         self
     }
 
-    fn done(self) -> XmlSecResult<()>
+    fn keyinfo(mut self, keyinfo: KeyInfoBuilder) -> Self
     {
-        let curi = {
-            if let Some(uri) = self.options.uri {
-                CString::new(uri).unwrap().into_raw() as *const c_uchar
-            } else {
-                null()
-            }
-        };
+        self.options.keyinfo = Some(keyinfo);
+        self
+    }
+
+    fn done(mut self) -> XmlSecResult<XmlNode>
+    {
+        let invalid = self.options.references.iter_mut()
+            .find_map(|reference| reference.invalid.take());
+
+        if let Some(err) = self.options.invalid.or(invalid) {
+            return Err(err);
+        }
+
+        if self.options.hmac_output_length.is_some() && ! self.options.sig.is_hmac() {
+            return Err("HMAC output length only applies to HMAC signature methods".into());
+        }
+
+        let placement = resolve_placement(self.doc, &self.options.placement)?;
 
         let c_ns_prefix = {
             if let Some(ns_prefix) = self.options.ns_prefix {
@@ -186,17 +386,18 @@ impl<'d> TemplateBuilder for XmlDocumentTemplateBuilder<'d>
 
         let docptr = self.doc.doc_ptr() as *mut bindings::xmlDoc;
 
-        let rootptr = if let Some(root) = self.doc.get_root_element() {
-            root.node_ptr() as *mut bindings::xmlNode
-        } else {
-            return Err(XmlSecError::RootNotFound);
-        };
+        let auto_ids = self.options.auto_ids;
+        let auto_id  = |id: Option<String>, prefix: &str| id.or_else(|| auto_ids.then(|| generate_id(docptr, prefix)));
+
+        let cid     = auto_id(self.options.id, "Signature").map(|id| CString::new(id).unwrap());
+        let csiid   = auto_id(self.options.signed_info_id, "SignedInfo").map(|id| CString::new(id).unwrap());
+        let ckeyiid = auto_id(self.options.keyinfo_id, "KeyInfo").map(|id| CString::new(id).unwrap());
 
         let signature = unsafe { bindings::xmlSecTmplSignatureCreateNsPref(
             docptr,
             self.options.c14n.to_method(),
             self.options.sig.to_method(),
-            null(),
+            cstr_ptr(&cid),
             c_ns_prefix,
         ) };
 
@@ -204,25 +405,53 @@ impl<'d> TemplateBuilder for XmlDocumentTemplateBuilder<'d>
             panic!("Failed to create signature template");
         }
 
-        let reference = unsafe { bindings::xmlSecTmplSignatureAddReference(
-            signature,
-            self.options.refsig.to_method(),
-            null(),
-            curi,
-            null(),
-        ) };
+        if let Some(bits) = self.options.hmac_output_length
+        {
+            let signmethod = unsafe { bindings::xmlSecTmplSignatureGetSignMethodNode(signature) };
 
-        if reference.is_null() {
-            panic!("Failed to add enveloped transform to reference");
+            if unsafe { bindings::xmlSecTmplTransformAddHmacOutputLength(signmethod, bits) } < 0 {
+                panic!("Failed to add HMAC output length");
+            }
+        }
+
+        if let Some(csiid) = csiid
+        {
+            let signedinfo = unsafe { (*bindings::xmlSecTmplSignatureGetC14NMethodNode(signature)).parent };
+
+            let name  = c"Id".as_ptr() as *const c_uchar;
+            let value = csiid.as_ptr() as *const c_uchar;
+
+            unsafe { bindings::xmlSetProp(signedinfo, name, value) };
         }
 
-        let envelope = unsafe { bindings::xmlSecTmplReferenceAddTransform(reference, bindings::xmlSecTransformEnvelopedGetKlass()) };
+        let mut references = self.options.references;
 
-        if envelope.is_null() {
-            panic!("Failed to add enveloped transform")
+        if references.is_empty() && self.options.objects.is_empty()
+        {
+            let mut reference = ReferenceBuilder::new()
+                .digest(self.options.refsig)
+                .enveloped(true);
+
+            reference.uri = self.options.uri;
+
+            references.push(reference);
+        }
+
+        for object in self.options.objects
+        {
+            let digest = self.options.refsig;
+
+            references.push(object.add_to(signature, docptr, digest));
+        }
+
+        for mut reference in references
+        {
+            reference.id = auto_id(reference.id, "Reference");
+
+            reference.add_to(signature);
         }
 
-        let keyinfo = unsafe { bindings::xmlSecTmplSignatureEnsureKeyInfo(signature, null()) };
+        let keyinfo = unsafe { bindings::xmlSecTmplSignatureEnsureKeyInfo(signature, cstr_ptr(&ckeyiid)) };
 
         if keyinfo.is_null() {
             panic!("Failed to ensure key info");
@@ -255,8 +484,900 @@ impl<'d> TemplateBuilder for XmlDocumentTemplateBuilder<'d>
             }
         }
 
-        unsafe { bindings::xmlAddChild(rootptr, signature) };
+        if let Some(builder) = self.options.keyinfo {
+            builder.add_to(keyinfo);
+        }
+
+        // an empty document gets the signature as its root, as is the case for detached signatures
+        match placement
+        {
+            Placement::Child(parent)   => unsafe { bindings::xmlAddChild(parent, signature) },
+            Placement::Before(sibling) => unsafe { bindings::xmlAddPrevSibling(sibling, signature) },
+            Placement::After(sibling)  => unsafe { bindings::xmlAddNextSibling(sibling, signature) },
+            Placement::Root            => unsafe { bindings::xmlDocSetRootElement(docptr, signature) },
+        };
+
+        let root = self.doc.get_root_element()
+            .ok_or(XmlSecError::RootNotFound)?;
+
+        wrap_raw_node(&root, signature)
+            .ok_or(XmlSecError::NodeNotFound)
+    }
+}
+
+/// Raw location resolved from a [`SignaturePlacement`], prior to building anything.
+enum Placement
+{
+    Child(*mut bindings::xmlNode),
+    Before(*mut bindings::xmlNode),
+    After(*mut bindings::xmlNode),
+    Root,
+}
+
+fn resolve_placement(doc: &XmlDocument, placement: &SignaturePlacement) -> XmlSecResult<Placement>
+{
+    let docptr = doc.doc_ptr() as *mut bindings::xmlDoc;
+
+    let within = |node: &XmlNode| -> XmlSecResult<*mut bindings::xmlNode> {
+        let nodeptr = node.node_ptr() as *mut bindings::xmlNode;
+
+        if unsafe { (*nodeptr).doc } != docptr {
+            return Err("Signature placement node does not belong to the templated document".into());
+        }
+
+        Ok(nodeptr)
+    };
+
+    let sibling = |node: &XmlNode| -> XmlSecResult<*mut bindings::xmlNode> {
+        if node.get_parent().is_none_or(|p| p.get_type() != Some(XmlNodeType::ElementNode)) {
+            return Err("Signature cannot be placed beside the document root".into());
+        }
+
+        within(node)
+    };
+
+    match placement
+    {
+        SignaturePlacement::Root => match doc.get_root_element()
+        {
+            Some(root) => Ok(Placement::Child(root.node_ptr() as *mut bindings::xmlNode)),
+            None       => Ok(Placement::Root),
+        },
+
+        SignaturePlacement::AppendTo(parent) => Ok(Placement::Child(within(parent)?)),
+        SignaturePlacement::Before(node)     => Ok(Placement::Before(sibling(node)?)),
+        SignaturePlacement::After(node)      => Ok(Placement::After(sibling(node)?)),
+
+        SignaturePlacement::XPath {expression, namespaces} => {
+            let xpath = XmlXPathContext::new(doc)
+                .map_err(|_| XmlSecError::from("Failed to build xpath context for signature placement"))?;
+
+            for (prefix, href) in namespaces {
+                xpath.register_namespace(prefix, href).ok();
+            }
+
+            let failed = || XmlSecError::from(format!("Failed to evaluate signature placement XPath: {}", expression));
+
+            let found = xpath.evaluate(expression)
+                .map_err(|_| failed())?
+                .get_nodes_as_vec()
+                .into_iter()
+                .next()
+                .ok_or(XmlSecError::NodeNotFound)?;
+
+            Ok(Placement::Child(within(&found)?))
+        },
+    }
+}
+
+/// Sub-builder describing a single `<dsig:Reference/>`, to be handed over to [`TemplateBuilder::reference`][addref].
+///
+/// [addref]: trait.TemplateBuilder.html#tymethod.reference
+pub struct ReferenceBuilder
+{
+    digest:   XmlSecDigestAlgorithm,
+    invalid:  Option<XmlSecError>,
+    uri:      Option<String>,
+    id:       Option<String>,
+    ref_type: Option<String>,
+
+    transforms: Vec<XmlSecTransform>,
+}
+
+impl ReferenceBuilder
+{
+    /// Starts a reference without URI, digested with SHA-1 and without transforms.
+    pub fn new() -> Self
+    {
+        Self {
+            digest:   XmlSecDigestAlgorithm::Sha1,
+            invalid:  None,
+            uri:      None,
+            id:       None,
+            ref_type: None,
+
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Sets the URI of the referenced data, e.g. `""` for the whole document or `"#id"` for an element within it.
+    pub fn uri(mut self, uri: &str) -> Self
+    {
+        self.uri = Some(uri.to_owned());
+        self
+    }
+
+    /// Sets the digest method of the reference. See: [`XmlSecDigestAlgorithm`][digest]. The deprecated
+    /// `XmlSecSignatureMethod` is still accepted, building the template failing if not a digest method.
+    ///
+    /// [digest]: enum.XmlSecDigestAlgorithm.html
+    pub fn digest<D>(mut self, digest: D) -> Self
+        where D: TryInto<XmlSecDigestAlgorithm>, XmlSecError: From<D::Error>
+    {
+        if let Some(digest) = checked_algorithm(digest, &mut self.invalid) {
+            self.digest = digest;
+        }
+
+        self
+    }
+
+    /// Sets the `Id` attribute of the reference.
+    pub fn id(mut self, id: &str) -> Self
+    {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    /// Sets the `Type` attribute of the reference, e.g. `http://uri.etsi.org/01903#SignedProperties` for XAdES.
+    pub fn ref_type(mut self, ref_type: &str) -> Self
+    {
+        self.ref_type = Some(ref_type.to_owned());
+        self
+    }
+
+    /// Adds the enveloped signature transform, needed whenever the signature lies within the referenced data.
+    /// Shorthand for adding [`XmlSecTransform::Enveloped`][enveloped] with [`transform`][transform].
+    ///
+    /// [enveloped]: ./transforms/enum.XmlSecTransform.html#variant.Enveloped
+    /// [transform]: #method.transform
+    pub fn enveloped(mut self, add: bool) -> Self
+    {
+        self.transforms.retain(|t| *t != XmlSecTransform::Enveloped);
+
+        if add {
+            self.transforms.push(XmlSecTransform::Enveloped);
+        }
+
+        self
+    }
+
+    /// Appends a transform to the chain applied to the referenced data. See: [`XmlSecTransform`][transform].
+    ///
+    /// [transform]: ./transforms/enum.XmlSecTransform.html
+    pub fn transform(mut self, transform: XmlSecTransform) -> Self
+    {
+        self.transforms.push(transform);
+        self
+    }
+
+    fn add_to(self, signature: *mut bindings::xmlNode)
+    {
+        let curi  = self.uri.map(|u| CString::new(u).unwrap());
+        let cid   = self.id.map(|i| CString::new(i).unwrap());
+        let ctype = self.ref_type.map(|t| CString::new(t).unwrap());
+
+        let reference = unsafe { bindings::xmlSecTmplSignatureAddReference(
+            signature,
+            self.digest.to_method(),
+            cstr_ptr(&cid),
+            cstr_ptr(&curi),
+            cstr_ptr(&ctype),
+        ) };
+
+        if reference.is_null() {
+            panic!("Failed to add reference to signature");
+        }
+
+        for transform in &self.transforms {
+            add_transform(reference, transform);
+        }
+    }
+}
+
+/// Sub-builder describing a single `<dsig:Object/>` carrying the signed content within the signature itself, to be
+/// handed over to [`TemplateBuilder::object`][addobj].
+///
+/// [addobj]: trait.TemplateBuilder.html#tymethod.object
+pub struct ObjectBuilder
+{
+    id:        String,
+    mime_type: Option<String>,
+    encoding:  Option<String>,
+
+    content: ObjectContent,
+}
+
+enum ObjectContent
+{
+    Node(XmlNode),
+    Data(Vec<u8>),
+}
+
+impl ObjectBuilder
+{
+    /// Starts an object with the given `Id` wrapping an XML node. A node of the document the template is built in is
+    /// moved into the object, one of any other document gets copied.
+    pub fn node(id: &str, node: &XmlNode) -> Self
+    {
+        Self {
+            id:        id.to_owned(),
+            mime_type: None,
+            encoding:  None,
+
+            content: ObjectContent::Node(node.clone()),
+        }
+    }
+
+    /// Starts an object with the given `Id` carrying raw bytes. These are stored base64 encoded, with the `Encoding`
+    /// attribute set accordingly, and the reference to them gets a base64 transform so that the signature covers the
+    /// raw bytes themselves.
+    pub fn data(id: &str, data: &[u8]) -> Self
+    {
+        Self {
+            id:        id.to_owned(),
+            mime_type: None,
+            encoding:  None,
+
+            content: ObjectContent::Data(data.to_vec()),
+        }
+    }
+
+    /// Sets the `MimeType` attribute of the object, e.g. `application/pdf`.
+    pub fn mime_type(mut self, mime_type: &str) -> Self
+    {
+        self.mime_type = Some(mime_type.to_owned());
+        self
+    }
+
+    /// Sets the `Encoding` attribute of the object, overriding the one set for raw bytes.
+    pub fn encoding(mut self, encoding: &str) -> Self
+    {
+        self.encoding = Some(encoding.to_owned());
+        self
+    }
+
+    /// Adds the object to the signature, returning the reference to it for the caller to add.
+    fn add_to(self, signature: *mut bindings::xmlNode, docptr: *mut bindings::xmlDoc, digest: XmlSecDigestAlgorithm)
+        -> ReferenceBuilder
+    {
+        let mut reference = ReferenceBuilder::new()
+            .uri(&format!("#{}", self.id))
+            .digest(digest);
+
+        let cid   = CString::new(self.id).unwrap();
+        let cmime = self.mime_type.map(|m| CString::new(m).unwrap());
+        let cenc  = self.encoding.map(|e| CString::new(e).unwrap());
+
+        let encoding = match (&self.content, &cenc)
+        {
+            (_, Some(cenc))             => cenc.as_ptr() as *const c_uchar,
+            (ObjectContent::Data(_), _) => unsafe { &bindings::xmlSecHrefBase64 as *const c_uchar },
+            _                           => null(),
+        };
+
+        let object = unsafe { bindings::xmlSecTmplSignatureAddObject(
+            signature,
+            cid.as_ptr() as *const c_uchar,
+            cstr_ptr(&cmime),
+            encoding,
+        ) };
+
+        if object.is_null() {
+            panic!("Failed to add object to signature");
+        }
+
+        match self.content
+        {
+            ObjectContent::Node(node) => {
+                let nodeptr = node.node_ptr() as *mut bindings::xmlNode;
+
+                let content = if unsafe { (*nodeptr).doc } == docptr {
+                    unsafe { bindings::xmlUnlinkNode(nodeptr) };
+                    nodeptr
+                } else {
+                    unsafe { bindings::xmlDocCopyNode(nodeptr, docptr, 1) }
+                };
+
+                if content.is_null() {
+                    panic!("Failed to copy object content");
+                }
+
+                unsafe { bindings::xmlAddChild(object, content) };
+            },
+
+            ObjectContent::Data(data) => {
+                let encoded = unsafe { bindings::xmlSecBase64Encode(data.as_ptr(), data.len() as u32, 0) };
+
+                if encoded.is_null() {
+                    panic!("Failed to encode object content");
+                }
+
+                unsafe { bindings::xmlNodeAddContent(object, encoded) };
 
-        Ok(())
+                if let Some(free) = unsafe { bindings::xmlFree } {
+                    unsafe { free(encoded as *mut c_void) };
+                }
+
+                reference = reference.transform(XmlSecTransform::Base64);
+            },
+        }
+
+        reference
     }
 }
+
+/// Sub-builder describing the content of `<dsig:KeyInfo/>`, to be handed over to [`TemplateBuilder::keyinfo`][addki].
+/// Content gets added in the order it is described, with X509 elements gathered within the current
+/// `<dsig:X509Data/>`, as started by [`x509_data`][x509data] or implicitly by the first of them. Empty X509 elements
+/// get filled in from the signing key and its certificates once signed, though xmlsec only fills the last one of
+/// each `<dsig:X509Data/>`, so that wanting several filled needs a `<dsig:X509Data/>` for each.
+///
+/// [addki]: trait.TemplateBuilder.html#tymethod.keyinfo
+/// [x509data]: #method.x509_data
+#[derive(Default)]
+pub struct KeyInfoBuilder
+{
+    items: Vec<KeyInfoItem>,
+}
+
+enum KeyInfoItem
+{
+    KeyName(Option<String>),
+    KeyValue,
+    RetrievalMethod { uri: String, rtype: Option<String> },
+
+    X509Data,
+    X509Certificate,
+    X509IssuerSerial,
+    X509SubjectName,
+    X509Ski,
+    X509Crl,
+}
+
+impl KeyInfoBuilder
+{
+    /// Starts an empty key information description.
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Adds `<dsig:KeyName/>`, carrying the given name or left empty for it to be filled in with the name of the key.
+    pub fn keyname(mut self, name: Option<&str>) -> Self
+    {
+        self.items.push(KeyInfoItem::KeyName(name.map(str::to_owned)));
+        self
+    }
+
+    /// Adds `<dsig:KeyValue/>`.
+    pub fn keyvalue(mut self) -> Self
+    {
+        self.items.push(KeyInfoItem::KeyValue);
+        self
+    }
+
+    /// Adds `<dsig:RetrievalMethod/>` pointing at key information elsewhere, e.g. `#cert` with type
+    /// `http://www.w3.org/2000/09/xmldsig#X509Data`.
+    pub fn retrieval_method(mut self, uri: &str, rtype: Option<&str>) -> Self
+    {
+        self.items.push(KeyInfoItem::RetrievalMethod {uri: uri.to_owned(), rtype: rtype.map(str::to_owned)});
+        self
+    }
+
+    /// Adds `<dsig:X509Data/>`, gathering the X509 elements described after it.
+    pub fn x509_data(mut self) -> Self
+    {
+        self.items.push(KeyInfoItem::X509Data);
+        self
+    }
+
+    /// Adds `<dsig:X509Certificate/>`.
+    pub fn x509_certificate(mut self) -> Self
+    {
+        self.items.push(KeyInfoItem::X509Certificate);
+        self
+    }
+
+    /// Adds `<dsig:X509IssuerSerial/>`.
+    pub fn x509_issuer_serial(mut self) -> Self
+    {
+        self.items.push(KeyInfoItem::X509IssuerSerial);
+        self
+    }
+
+    /// Adds `<dsig:X509SubjectName/>`.
+    pub fn x509_subject_name(mut self) -> Self
+    {
+        self.items.push(KeyInfoItem::X509SubjectName);
+        self
+    }
+
+    /// Adds `<dsig:X509SKI/>`.
+    pub fn x509_ski(mut self) -> Self
+    {
+        self.items.push(KeyInfoItem::X509Ski);
+        self
+    }
+
+    /// Adds `<dsig:X509CRL/>`.
+    pub fn x509_crl(mut self) -> Self
+    {
+        self.items.push(KeyInfoItem::X509Crl);
+        self
+    }
+
+    fn add_to(self, keyinfo: *mut bindings::xmlNode)
+    {
+        let mut x509data: *mut bindings::xmlNode = null_mut();
+
+        for item in self.items
+        {
+            let node = match item
+            {
+                KeyInfoItem::KeyName(name) => {
+                    let cname = name.map(|n| CString::new(n).unwrap());
+
+                    unsafe { bindings::xmlSecTmplKeyInfoAddKeyName(keyinfo, cstr_ptr(&cname)) }
+                },
+
+                KeyInfoItem::KeyValue => unsafe { bindings::xmlSecTmplKeyInfoAddKeyValue(keyinfo) },
+
+                KeyInfoItem::RetrievalMethod {uri, rtype} => {
+                    let curi   = CString::new(uri).unwrap();
+                    let crtype = rtype.map(|t| CString::new(t).unwrap());
+
+                    unsafe { bindings::xmlSecTmplKeyInfoAddRetrievalMethod(
+                        keyinfo,
+                        curi.as_ptr() as *const c_uchar,
+                        cstr_ptr(&crtype),
+                    ) }
+                },
+
+                KeyInfoItem::X509Data => {
+                    x509data = unsafe { bindings::xmlSecTmplKeyInfoAddX509Data(keyinfo) };
+                    x509data
+                },
+
+                x509 => {
+                    if x509data.is_null()
+                    {
+                        x509data = unsafe { bindings::xmlSecTmplKeyInfoAddX509Data(keyinfo) };
+
+                        if x509data.is_null() {
+                            panic!("Failed to add x509 data");
+                        }
+                    }
+
+                    unsafe {
+                        match x509
+                        {
+                            KeyInfoItem::X509Certificate  => bindings::xmlSecTmplX509DataAddCertificate(x509data),
+                            KeyInfoItem::X509IssuerSerial => bindings::xmlSecTmplX509DataAddIssuerSerial(x509data),
+                            KeyInfoItem::X509SubjectName  => bindings::xmlSecTmplX509DataAddSubjectName(x509data),
+                            KeyInfoItem::X509Ski          => bindings::xmlSecTmplX509DataAddSKI(x509data),
+                            KeyInfoItem::X509Crl          => bindings::xmlSecTmplX509DataAddCRL(x509data),
+                            _                             => unreachable!(),
+                        }
+                    }
+                },
+            };
+
+            if node.is_null() {
+                panic!("Failed to add key information");
+            }
+        }
+    }
+}
+
+/// Declaration of an encryption template building API for other specific trait extensions on foreign XML objects.
+pub trait EncryptionTemplateBuilder
+{
+    /// Sets the method used to encrypt the data, usually one of the [`XmlSecBlockCipher`][cipher], else a
+    /// [`XmlSecKeyTransport`][keytrans] for data as small as a key.
+    ///
+    /// [cipher]: enum.XmlSecBlockCipher.html
+    /// [keytrans]: enum.XmlSecKeyTransport.html
+    fn method<M: XmlSecEncryptionMethod>(self, method: M) -> Self;
+
+    /// Sets the `Type` of the encrypted data. See: [`XmlSecEncryptionType`][enctype].
+    ///
+    /// [enctype]: ./xmlenc/enum.XmlSecEncryptionType.html
+    fn enctype(self, enctype: XmlSecEncryptionType) -> Self;
+
+    /// Sets the `Id` attribute of `<enc:EncryptedData/>`
+    fn id(self, id: &str) -> Self;
+
+    /// Sets the `MimeType` attribute of `<enc:EncryptedData/>`
+    fn mime_type(self, mime_type: &str) -> Self;
+
+    /// Sets the `Encoding` attribute of `<enc:EncryptedData/>`
+    fn encoding(self, encoding: &str) -> Self;
+
+    /// Makes the cipher data point to the given URI with `<enc:CipherReference/>` instead of carrying it within
+    /// `<enc:CipherValue/>`
+    fn cipher_reference(self, uri: &str) -> Self;
+
+    /// Adds <ds:KeyName> to key information node
+    fn keyname(self, add: bool) -> Self;
+
+    /// Adds a nested <enc:EncryptedKey> to key information node, encrypting the data key with the given key transport
+    /// or key wrap method. See: [`XmlSecKeyTransport`][keytrans]. The deprecated `XmlSecSignatureMethod` is still
+    /// accepted, building the template failing if not a key transport.
+    ///
+    /// [keytrans]: enum.XmlSecKeyTransport.html
+    fn encrypted_key<M>(self, method: M) -> Self
+        where M: TryInto<XmlSecKeyTransport>, XmlSecError: From<M::Error>;
+
+    /// Adds <ds:KeyName> to key information node of the nested <enc:EncryptedKey>
+    fn encrypted_keyname(self, add: bool) -> Self;
+
+    /// Builds the actual template and returns it
+    fn done(self) -> XmlSecResult<XmlNode>;
+}
+
+/// Trait extension aimed at a concrete encryption template implementation for [`XmlDocument`][xmldoc]
+///
+/// [xmldoc]: http://kwarc.github.io/rust-libxml/libxml/tree/document/struct.Document.html
+pub trait XmlDocumentEncryptionTemplating<'d>
+{
+    /// Return an encryption template builder over current XmlDocument.
+    fn encryption_template(&'d self) -> XmlDocumentEncryptionTemplateBuilder<'d>;
+}
+
+/// Concrete encryption template builder for [`XmlDocument`][xmldoc]. The resulting `<enc:EncryptedData/>` becomes the
+/// root of the document if it is empty, otherwise it is returned unlinked for it to be placed by the encryption.
+///
+/// [xmldoc]: http://kwarc.github.io/rust-libxml/libxml/tree/document/struct.Document.html
+pub struct XmlDocumentEncryptionTemplateBuilder<'d>
+{
+    doc:     &'d XmlDocument,
+    options: EncryptionTemplateOptions,
+}
+
+struct EncryptionTemplateOptions
+{
+    method:  bindings::xmlSecTransformId,
+    enctype: Option<XmlSecEncryptionType>,
+
+    id:        Option<String>,
+    mime_type: Option<String>,
+    encoding:  Option<String>,
+    cipherref: Option<String>,
+
+    keyname: bool,
+
+    enckey:     Option<XmlSecKeyTransport>,
+    enckeyname: bool,
+
+    invalid: Option<XmlSecError>,
+}
+
+impl Default for EncryptionTemplateOptions
+{
+    fn default() -> Self
+    {
+        Self {
+            method:  XmlSecBlockCipher::Aes128Cbc.to_method(),
+            enctype: None,
+
+            id:        None,
+            mime_type: None,
+            encoding:  None,
+            cipherref: None,
+
+            keyname: false,
+
+            enckey:     None,
+            enckeyname: false,
+
+            invalid: None,
+        }
+    }
+}
+
+impl<'d> XmlDocumentEncryptionTemplating<'d> for XmlDocument
+{
+    fn encryption_template(&'d self) -> XmlDocumentEncryptionTemplateBuilder<'d>
+    {
+        crate::xmlsec::guarantee_xmlsec_init();
+
+        XmlDocumentEncryptionTemplateBuilder {doc: self, options: EncryptionTemplateOptions::default()}
+    }
+}
+
+impl<'d> EncryptionTemplateBuilder for XmlDocumentEncryptionTemplateBuilder<'d>
+{
+    fn method<M: XmlSecEncryptionMethod>(mut self, method: M) -> Self
+    {
+        self.options.method = method.to_method();
+        self
+    }
+
+    fn enctype(mut self, enctype: XmlSecEncryptionType) -> Self
+    {
+        self.options.enctype = Some(enctype);
+        self
+    }
+
+    fn id(mut self, id: &str) -> Self
+    {
+        self.options.id = Some(id.to_owned());
+        self
+    }
+
+    fn mime_type(mut self, mime_type: &str) -> Self
+    {
+        self.options.mime_type = Some(mime_type.to_owned());
+        self
+    }
+
+    fn encoding(mut self, encoding: &str) -> Self
+    {
+        self.options.encoding = Some(encoding.to_owned());
+        self
+    }
+
+    fn cipher_reference(mut self, uri: &str) -> Self
+    {
+        self.options.cipherref = Some(uri.to_owned());
+        self
+    }
+
+    fn keyname(mut self, add: bool) -> Self
+    {
+        self.options.keyname = add;
+        self
+    }
+
+    fn encrypted_key<M>(mut self, method: M) -> Self
+        where M: TryInto<XmlSecKeyTransport>, XmlSecError: From<M::Error>
+    {
+        if let Some(method) = checked_algorithm(method, &mut self.options.invalid) {
+            self.options.enckey = Some(method);
+        }
+
+        self
+    }
+
+    fn encrypted_keyname(mut self, add: bool) -> Self
+    {
+        self.options.enckeyname = add;
+        self
+    }
+
+    fn done(self) -> XmlSecResult<XmlNode>
+    {
+        if let Some(err) = self.options.invalid {
+            return Err(err);
+        }
+
+        let cid        = self.options.id.map(|id| CString::new(id).unwrap());
+        let cmime_type = self.options.mime_type.map(|mt| CString::new(mt).unwrap());
+        let cencoding  = self.options.encoding.map(|en| CString::new(en).unwrap());
+        let ccipherref = self.options.cipherref.map(|uri| CString::new(uri).unwrap());
+
+        let enctype = self.options.enctype
+            .map(|t| t.to_href())
+            .unwrap_or(null());
+
+        let docptr = self.doc.doc_ptr() as *mut bindings::xmlDoc;
+
+        let encdata = unsafe { bindings::xmlSecTmplEncDataCreate(
+            docptr,
+            self.options.method,
+            cstr_ptr(&cid),
+            enctype,
+            cstr_ptr(&cmime_type),
+            cstr_ptr(&cencoding),
+        ) };
+
+        if encdata.is_null() {
+            panic!("Failed to create encryption template");
+        }
+
+        let cipher = if let Some(uri) = &ccipherref {
+            unsafe { bindings::xmlSecTmplEncDataEnsureCipherReference(encdata, uri.as_ptr() as *const c_uchar) }
+        } else {
+            unsafe { bindings::xmlSecTmplEncDataEnsureCipherValue(encdata) }
+        };
+
+        if cipher.is_null() {
+            panic!("Failed to add cipher data");
+        }
+
+        if self.options.keyname || self.options.enckey.is_some()
+        {
+            let keyinfo = unsafe { bindings::xmlSecTmplEncDataEnsureKeyInfo(encdata, null()) };
+
+            if keyinfo.is_null() {
+                panic!("Failed to ensure key info");
+            }
+
+            if self.options.keyname
+            {
+                let keyname = unsafe { bindings::xmlSecTmplKeyInfoAddKeyName(keyinfo, null()) };
+
+                if keyname.is_null() {
+                    panic!("Failed to add key name");
+                }
+            }
+
+            if let Some(method) = self.options.enckey
+            {
+                let enckey = unsafe { bindings::xmlSecTmplKeyInfoAddEncryptedKey(
+                    keyinfo,
+                    method.to_method(),
+                    null(),
+                    null(),
+                    null(),
+                ) };
+
+                if enckey.is_null() {
+                    panic!("Failed to add encrypted key");
+                }
+
+                let enckeyvalue = unsafe { bindings::xmlSecTmplEncDataEnsureCipherValue(enckey) };
+
+                if enckeyvalue.is_null() {
+                    panic!("Failed to add encrypted key cipher value");
+                }
+
+                if self.options.enckeyname
+                {
+                    let enckeyinfo = unsafe { bindings::xmlSecTmplEncDataEnsureKeyInfo(enckey, null()) };
+
+                    if enckeyinfo.is_null() {
+                        panic!("Failed to ensure encrypted key info");
+                    }
+
+                    let keyname = unsafe { bindings::xmlSecTmplKeyInfoAddKeyName(enckeyinfo, null()) };
+
+                    if keyname.is_null() {
+                        panic!("Failed to add encrypted key name");
+                    }
+                }
+            }
+        }
+
+        wrap_unlinked_node(self.doc, encdata)
+            .ok_or(XmlSecError::NodeNotFound)
+    }
+}
+
+fn add_transform(reference: *mut bindings::xmlNode, transform: &XmlSecTransform)
+{
+    let node = unsafe { bindings::xmlSecTmplReferenceAddTransform(reference, transform.to_method()) };
+
+    if node.is_null() {
+        panic!("Failed to add transform to reference");
+    }
+
+    let rc = match transform
+    {
+        XmlSecTransform::ExclusiveC14N {prefix_list, ..} if ! prefix_list.is_empty() => {
+            let cprefixes = CString::new(prefix_list.join(" ")).unwrap();
+
+            unsafe { bindings::xmlSecTmplTransformAddC14NInclNamespaces(node, cprefixes.as_ptr() as *const c_uchar) }
+        },
+
+        XmlSecTransform::XPath {expression, namespaces} => {
+            let cexpr = CString::new(expression.as_str()).unwrap();
+            let cns   = NsList::new(namespaces);
+
+            unsafe { bindings::xmlSecTmplTransformAddXPath(node, cexpr.as_ptr() as *const c_uchar, cns.as_ptr()) }
+        },
+
+        XmlSecTransform::XPath2 {filter, expression, namespaces} => {
+            let cexpr = CString::new(expression.as_str()).unwrap();
+            let cns   = NsList::new(namespaces);
+
+            unsafe { bindings::xmlSecTmplTransformAddXPath2(
+                node,
+                filter.to_type(),
+                cexpr.as_ptr() as *const c_uchar,
+                cns.as_ptr()
+            ) }
+        },
+
+        XmlSecTransform::Xslt(stylesheet) => {
+            let cxslt = CString::new(stylesheet.as_str()).unwrap();
+
+            unsafe { bindings::xmlSecTmplTransformAddXsltStylesheet(node, cxslt.as_ptr() as *const c_uchar) }
+        },
+
+        _ => 0,
+    };
+
+    if rc < 0 {
+        panic!("Failed to setup transform of reference");
+    }
+}
+
+
+/// NULL terminated list of namespace prefix and href pairs, as expected by xmlsec
+struct NsList
+{
+    _strings: Vec<CString>,
+    pointers: Vec<*const c_uchar>,
+}
+
+impl NsList
+{
+    fn new(namespaces: &[(String, String)]) -> Self
+    {
+        let strings: Vec<CString> = namespaces.iter()
+            .flat_map(|(prefix, href)| [prefix, href])
+            .map(|s| CString::new(s.as_str()).unwrap())
+            .collect();
+
+        let mut pointers: Vec<*const c_uchar> = strings.iter()
+            .map(|s| s.as_ptr() as *const c_uchar)
+            .collect();
+
+        pointers.push(null());
+
+        Self {_strings: strings, pointers}
+    }
+
+    fn as_ptr(&self) -> *mut *const c_uchar
+    {
+        // xmlsec only reads through it despite the signature
+        self.pointers.as_ptr() as *mut *const c_uchar
+    }
+}
+
+
+/// Generates an `Id` not yet registered within the document, e.g. `Signature-8f3a09c1d2e4b657`.
+fn generate_id(docptr: *mut bindings::xmlDoc, prefix: &str) -> String
+{
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    loop
+    {
+        // randomly keyed on each instantiation, avoiding clashes across documents and processes
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+
+        let id  = format!("{}-{:016x}", prefix, hasher.finish());
+        let cid = CString::new(id.clone()).unwrap();
+
+        if unsafe { bindings::xmlGetID(docptr, cid.as_ptr() as *const c_uchar) }.is_null() {
+            return id;
+        }
+    }
+}
+
+
+/// Converts an algorithm handed to a builder into the kind expected, keeping the first failure to do so in `invalid`
+/// for the template to be refused once built.
+fn checked_algorithm<A, T>(algorithm: A, invalid: &mut Option<XmlSecError>) -> Option<T>
+    where A: TryInto<T>, XmlSecError: From<A::Error>
+{
+    match algorithm.try_into()
+    {
+        Ok(algorithm) => Some(algorithm),
+
+        Err(err) => {
+            invalid.get_or_insert(err.into());
+
+            None
+        }
+    }
+}
+
+fn cstr_ptr(cstr: &Option<CString>) -> *const c_uchar
+{
+    cstr.as_ref()
+        .map(|c| c.as_ptr() as *const c_uchar)
+        .unwrap_or(null())
+}
@@ -3,9 +3,12 @@
 //!
 use crate::bindings;
 
+use std::os::raw::c_uchar;
+
 
 /// Supported canonical methods as specified by the XML standard.
 #[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum XmlSecCanonicalizationMethod
 {
     InclusiveC14N,
@@ -33,3 +36,102 @@ impl XmlSecCanonicalizationMethod
         }
     }
 }
+
+
+/// Transforms applicable to the data of a `<dsig:Reference/>` before digesting it, in the order they are added.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum XmlSecTransform
+{
+    /// Removes the signature itself from the referenced data, required whenever it lies within said data.
+    Enveloped,
+
+    /// Canonicalizes the referenced data with the given method.
+    C14N(XmlSecCanonicalizationMethod),
+
+    /// Exclusive canonicalization treating the namespaces of the listed prefixes as inclusive, as found in
+    /// `<ec:InclusiveNamespaces PrefixList="..."/>`. Use `#default` for the default namespace.
+    ExclusiveC14N
+    {
+        /// Whether to keep comments.
+        with_comments: bool,
+        /// Prefixes of the namespaces to treat as inclusive.
+        prefix_list: Vec<String>,
+    },
+
+    /// Filters the referenced nodes with an XPath expression, evaluated for each of them.
+    XPath
+    {
+        /// XPath expression, e.g. `not(ancestor-or-self::dsig:Signature)`.
+        expression: String,
+        /// Namespaces used by the expression, as prefix and href pairs.
+        namespaces: Vec<(String, String)>,
+    },
+
+    /// Filters the referenced nodes with an XPath Filter 2.0 expression.
+    XPath2
+    {
+        /// How the selected nodes are combined with the referenced ones.
+        filter: XmlSecXPath2Filter,
+        /// XPath expression selecting nodes, e.g. `//ToBeSigned`.
+        expression: String,
+        /// Namespaces used by the expression, as prefix and href pairs.
+        namespaces: Vec<(String, String)>,
+    },
+
+    /// Decodes base64 encoded content.
+    Base64,
+
+    /// Applies the given XSLT stylesheet.
+    Xslt(String),
+}
+
+
+/// Set operations of XPath Filter 2.0.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecXPath2Filter
+{
+    Intersect,
+    Subtract,
+    Union,
+}
+
+
+impl XmlSecTransform
+{
+    /// Returns the resource pointer for the corresponding transform resource
+    pub fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        match self
+        {
+            Self::Enveloped => unsafe { bindings::xmlSecTransformEnvelopedGetKlass() },
+            Self::C14N(c14n) => c14n.to_method(),
+
+            Self::ExclusiveC14N {with_comments, ..} => match with_comments
+            {
+                false => XmlSecCanonicalizationMethod::ExclusiveC14N.to_method(),
+                true  => XmlSecCanonicalizationMethod::ExclusiveC14NWithComments.to_method(),
+            },
+
+            Self::XPath  {..} => unsafe { bindings::xmlSecTransformXPathGetKlass() },
+            Self::XPath2 {..} => unsafe { bindings::xmlSecTransformXPath2GetKlass() },
+            Self::Base64      => unsafe { bindings::xmlSecTransformBase64GetKlass() },
+            Self::Xslt(_)     => unsafe { bindings::xmlSecTransformXsltGetKlass() },
+        }
+    }
+}
+
+
+impl XmlSecXPath2Filter
+{
+    /// Returns the filter type as expected in the `Filter` attribute
+    pub fn to_type(self) -> *const c_uchar
+    {
+        match self
+        {
+            Self::Intersect => unsafe { &bindings::xmlSecXPath2FilterIntersect as *const c_uchar },
+            Self::Subtract  => unsafe { &bindings::xmlSecXPath2FilterSubtract  as *const c_uchar },
+            Self::Union     => unsafe { &bindings::xmlSecXPath2FilterUnion     as *const c_uchar },
+        }
+    }
+}
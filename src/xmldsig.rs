@@ -4,21 +4,184 @@
 use crate::bindings;
 
 use crate::XmlSecKey;
+use crate::XmlSecKeysManager;
 use crate::XmlSecError;
 use crate::XmlSecResult;
+use crate::XmlSecSignatureAlgorithm;
+use crate::XmlSecDigestAlgorithm;
+use crate::XmlSecCanonicalizationMethod;
+use crate::XmlSecTransform;
+use crate::crypto::backend;
+use crate::error::clear_records;
+use crate::error::library_error;
+use crate::xmlsec::acquire_xmlsec;
+use crate::xmlsec::release_xmlsec;
+use crate::xmlsec::reset_context;
+use crate::error::policy_error;
 
 use crate::XmlNode;
 use crate::XmlDocument;
 
+use crate::exts::wrap_raw_node;
+use crate::io::ResolverGuard;
+use crate::XmlSecUriResolver;
+
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::os::raw::c_char;
 use std::os::raw::c_uchar;
+use std::os::raw::c_void;
 use std::mem::forget;
+use std::ptr::null;
 use std::ptr::null_mut;
+use std::rc::Rc;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+
+/// Options on how certificates within `<ds:X509Data/>` get processed when reading a `<ds:KeyInfo/>`.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XmlSecX509Flag
+{
+    /// Loads certificates without validating them against the trusted ones. Meant for testing only.
+    DontVerifyCerts    = bindings::XMLSEC_KEYINFO_FLAGS_X509DATA_DONT_VERIFY_CERTS,
+    /// Aborts on unknown children of `<ds:X509Data/>`.
+    StopOnUnknownChild = bindings::XMLSEC_KEYINFO_FLAGS_X509DATA_STOP_ON_UNKNOWN_CHILD,
+    /// Aborts when a certificate referenced by issuer/serial, SKI or subject name cannot be resolved.
+    StopOnUnknownCert  = bindings::XMLSEC_KEYINFO_FLAGS_X509DATA_STOP_ON_UNKNOWN_CERT,
+    /// Aborts when no verified certificate results from processing `<ds:X509Data/>`.
+    StopOnInvalidCert  = bindings::XMLSEC_KEYINFO_FLAGS_X509DATA_STOP_ON_INVALID_CERT,
+    /// Skips strict checks of certificates and revocation lists.
+    SkipStrictChecks   = bindings::XMLSEC_KEYINFO_FLAGS_X509DATA_SKIP_STRICT_CHECKS,
+}
+
+
+/// Kinds of data `<ds:Reference/>` are allowed to point at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecReferenceUris
+{
+    /// Only the document holding the signature, either whole (`URI=""`) or fragments of it (`URI="#id"`).
+    SameDocument,
+    /// As above, plus local files.
+    Local,
+    /// Anything, including remote resources.
+    Any,
+}
+
+
+/// Outcome of a signature or reference verification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecVerificationStatus
+{
+    /// Not verified (yet), e.g. because processing stopped before reaching it.
+    Unknown,
+    /// Digest or signature value matched.
+    Succeeded,
+    /// Digest or signature value did not match.
+    Invalid,
+}
+
+
+/// Where a `<ds:Reference/>` was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlSecReferenceOrigin
+{
+    /// Directly within `<ds:SignedInfo/>`.
+    SignedInfo,
+    /// Within a `<ds:Manifest/>` referenced by the signature.
+    Manifest,
+}
+
+
+/// Report on the last verification run by a [`XmlSecSignatureContext`][sigctx], as returned by its
+/// [`verification_report`][report] method.
+///
+/// [sigctx]: struct.XmlSecSignatureContext.html
+/// [report]: struct.XmlSecSignatureContext.html#method.verification_report
+#[derive(Clone, Debug)]
+pub struct XmlSecVerificationReport
+{
+    /// Overall status of the signature.
+    pub status: XmlSecVerificationStatus,
+
+    /// Signature method, if processed and known to this wrapper.
+    pub signature_method: Option<XmlSecSignatureAlgorithm>,
+    /// Algorithm URI of the signature method, if processed.
+    pub signature_method_uri: Option<String>,
+    /// Algorithm URI of the canonicalization method applied to `<ds:SignedInfo/>`, if processed.
+    pub canonicalization_uri: Option<String>,
+
+    /// Name of the key the signature was verified with, if any.
+    pub key_name: Option<String>,
+    /// Subject of the certificate bound to the key the signature was verified with, if any. Only reported with the
+    /// OpenSSL crypto backend.
+    pub certificate_subject: Option<String>,
+    /// Whether said certificate chains up to the trusted certificates of the keys manager. See
+    /// [`certificate_verified`][certver].
+    ///
+    /// [certver]: struct.XmlSecSignatureContext.html#method.certificate_verified
+    pub certificate_verified: bool,
+
+    /// References processed while verifying, in document order, those of `<ds:SignedInfo/>` coming first.
+    pub references: Vec<XmlSecReferenceReport>,
+}
+
+
+/// Outcome of verifying one of the signatures of a document, as returned by
+/// [`XmlSecSignatureContext::verify_all`][verifyall].
+///
+/// [verifyall]: struct.XmlSecSignatureContext.html#method.verify_all
+#[derive(Debug)]
+pub struct XmlSecSignatureReport
+{
+    /// XPath expression locating the `<ds:Signature/>` within the document, as built by libxml.
+    pub xpath: String,
+    /// Whether the signature is valid.
+    pub valid: bool,
+    /// Error the verification failed with, if it could not be carried out, e.g. when no key was found for it.
+    pub error: Option<XmlSecError>,
+    /// Details of the verification, including the key it was checked with.
+    pub report: XmlSecVerificationReport,
+}
+
+
+/// Report on a single `<ds:Reference/>` processed during verification.
+#[derive(Clone, Debug)]
+pub struct XmlSecReferenceReport
+{
+    /// Where the reference was found.
+    pub origin: XmlSecReferenceOrigin,
+    /// Status of the digest computed over the referenced data compared to the `<ds:DigestValue/>`.
+    pub status: XmlSecVerificationStatus,
+
+    /// `Id` attribute of the reference, if any.
+    pub id: Option<String>,
+    /// `URI` attribute of the reference, if any.
+    pub uri: Option<String>,
+    /// `Type` attribute of the reference, if any.
+    pub ref_type: Option<String>,
+
+    /// Digest method, if processed and known to this wrapper.
+    pub digest_method: Option<XmlSecDigestAlgorithm>,
+    /// Algorithm URI of the digest method, if processed.
+    pub digest_method_uri: Option<String>,
+
+    /// Data that was fed into the digest, available for references of `<ds:SignedInfo/>` if the context was told to
+    /// keep it with [`set_store_references`][storerefs].
+    ///
+    /// [storerefs]: struct.XmlSecSignatureContext.html#method.set_store_references
+    pub pre_digest: Option<Vec<u8>>,
+}
 
 
 /// Signature signing/veryfying context
 pub struct XmlSecSignatureContext
 {
-    ctx: *mut bindings::xmlSecDSigCtx,
+    ctx:      *mut bindings::xmlSecDSigCtx,
+    mngr:     Option<XmlSecKeysManager>,
+    resolver: Option<XmlSecUriResolver>,
+    own_key:  bool,
 }
 
 
@@ -27,7 +190,7 @@ impl XmlSecSignatureContext
     /// Builds a context, ensuring xmlsec is initialized.
     pub fn new() -> Self
     {
-        crate::xmlsec::guarantee_xmlsec_init();
+        acquire_xmlsec();
 
         let ctx = unsafe { bindings::xmlSecDSigCtxCreate(null_mut()) };
 
@@ -35,7 +198,25 @@ impl XmlSecSignatureContext
             panic!("Failed to create dsig context");
         }
 
-        Self {ctx}
+        Self {ctx, mngr: None, resolver: None, own_key: false}
+    }
+
+    /// Builds a context that resolves keys through the given [`XmlSecKeysManager`][mngr] from the `<ds:KeyInfo/>`
+    /// being processed, unless one is explicitly set with [`insert_key`][inskey].
+    ///
+    /// [mngr]: struct.XmlSecKeysManager.html
+    /// [inskey]: #method.insert_key
+    pub fn with_keys_manager(mngr: &XmlSecKeysManager) -> Self
+    {
+        acquire_xmlsec();
+
+        let ctx = unsafe { bindings::xmlSecDSigCtxCreate(mngr.as_ptr()) };
+
+        if ctx.is_null() {
+            panic!("Failed to create dsig context");
+        }
+
+        Self {ctx, mngr: Some(mngr.clone()), resolver: None, own_key: false}
     }
 
     /// Sets the key to use for signature or verification. In case a key had
@@ -52,6 +233,8 @@ impl XmlSecSignatureContext
             (*self.ctx).signKey = XmlSecKey::leak(key);
         }
 
+        self.own_key = true;
+
         old
     }
 
@@ -66,23 +249,35 @@ impl XmlSecSignatureContext
 
                 (*self.ctx).signKey = null_mut();
 
+                self.own_key = false;
+
                 Some(key)
             }
         }
     }
 
-    /// UNTESTED
+    /// Signs a single `<dsig:Signature/>` template, given either the signature node itself or the root of a subtree
+    /// containing it. Within a subtree, the signature placed directly under its root is taken, as is the case with
+    /// the signed element of SAML, otherwise the search carries on the same way down each of its children in turn.
+    /// Other signatures in the document are left untouched, hence nested ones are to be signed from the innermost
+    /// outwards.
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set, no signature is found within the subtree or it is malformed.
     pub fn sign_node(&self, node: &XmlNode) -> XmlSecResult<()>
     {
-        self.key_is_set()?;
+        self.key_is_available()?;
 
-        let node = node.node_ptr() as bindings::xmlNodePtr;
+        let sig = find_subtree_signode(node.node_ptr() as *mut bindings::xmlNode)?;
 
-        self.sign_node_raw(node)
+        self.sign_node_raw(sig)
     }
 
     /// Takes a [`XmlDocument`][xmldoc] and attempts to sign it. For this to work it has to have a properly structured
-    /// `<dsig:Signature>` node within, and a XmlSecKey must have been previously set with [`insert_key`][inskey].
+    /// `<dsig:Signature>` node within, and a XmlSecKey must have been previously set with [`insert_key`][inskey] or be
+    /// resolvable through the keys manager of the context. Of several signatures, the first in document order is the
+    /// one signed; see [`sign_node`][signnode] and [`sign_all`][signall] for others.
     ///
     /// # Errors
     ///
@@ -90,29 +285,39 @@ impl XmlSecSignatureContext
     ///
     /// [xmldoc]: http://kwarc.github.io/rust-libxml/libxml/tree/document/struct.Document.html
     /// [inskey]: struct.XmlSecSignatureContext.html#method.insert_key
+    /// [signnode]: #method.sign_node
+    /// [signall]: #method.sign_all
     pub fn sign_document(&self, doc: &XmlDocument) -> XmlSecResult<()>
     {
-        self.key_is_set()?;
+        self.key_is_available()?;
 
-        let root = find_root(doc)?;
-        let sig  = find_signode(root)?;
+        let sig = find_signode(find_root(doc)?)?;
 
         self.sign_node_raw(sig)
     }
 
-    /// UNTESTED
+    /// Verifies a single `<dsig:Signature/>`, given either the signature node itself or the root of a subtree
+    /// containing it, located the same way as with [`sign_node`][signnode].
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set, no signature is found within the subtree or it is malformed.
+    ///
+    /// [signnode]: #method.sign_node
     pub fn verify_node(&self, node: &XmlNode) -> XmlSecResult<bool>
     {
-        self.key_is_set()?;
+        self.key_is_available()?;
 
-        let node = node.node_ptr() as bindings::xmlNodePtr;
+        let sig = find_subtree_signode(node.node_ptr() as *mut bindings::xmlNode)?;
 
-        self.verify_node_raw(node)
+        self.verify_node_raw(sig)
     }
 
     /// Takes a [`XmlDocument`][xmldoc] and attempts to verify its signature. For this to work it has to have a properly
     /// structured and signed `<dsig:Signature>` node within, and a XmlSecKey must have been previously set with
-    /// [`insert_key`][inskey].
+    /// [`insert_key`][inskey] or be resolvable through the keys manager of the context. Of several signatures, the
+    /// first in document order is the one verified; see [`verify_node`][verifynode] and [`verify_all`][verifyall] for
+    /// others.
     ///
     /// # Errors
     ///
@@ -120,16 +325,354 @@ impl XmlSecSignatureContext
     ///
     /// [xmldoc]: http://kwarc.github.io/rust-libxml/libxml/tree/document/struct.Document.html
     /// [inskey]: struct.XmlSecSignatureContext.html#method.insert_key
+    /// [verifynode]: #method.verify_node
+    /// [verifyall]: #method.verify_all
     pub fn verify_document(&self, doc: &XmlDocument) -> XmlSecResult<bool>
     {
-        self.key_is_set()?;
+        self.key_is_available()?;
 
-        let root = find_root(doc)?;
-        let sig  = find_signode(root)?;
+        let sig = find_signode(find_root(doc)?)?;
 
         self.verify_node_raw(sig)
     }
 
+    /// Signs every `<dsig:Signature/>` template found in the document, e.g. those of each DTE within an SII
+    /// `EnvioDTE` and the one of the envelope itself. Signatures placed deeper get signed first, so that those nested
+    /// within the data of another are done by the time the latter gets digested, while ones at the same depth follow
+    /// document order. Keys are resolved as with [`sign_document`][signdoc] for each of them.
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set, the document holds no signature or signing any of them fails, in which case
+    /// those signed until then remain so.
+    ///
+    /// [signdoc]: #method.sign_document
+    pub fn sign_all(&self, doc: &XmlDocument) -> XmlSecResult<()>
+    {
+        self.key_is_available()?;
+
+        let mut signodes = find_signodes(find_root(doc)?);
+
+        if signodes.is_empty() {
+            return Err(XmlSecError::NodeNotFound);
+        }
+
+        // stable, hence document order is kept among equally deep ones
+        signodes.sort_by_key(|node| std::cmp::Reverse(node_depth(*node)));
+
+        for signode in signodes {
+            self.sign_node_raw(signode)?;
+        }
+
+        Ok(())
+    }
+
+    /// Verifies every `<dsig:Signature/>` found in the document, in document order, reporting on each of them rather
+    /// than stopping at the first failure. The document is only to be trusted if all of them turn out valid.
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set or the document holds no signature at all.
+    pub fn verify_all(&self, doc: &XmlDocument) -> XmlSecResult<Vec<XmlSecSignatureReport>>
+    {
+        self.key_is_available()?;
+
+        let signodes = find_signodes(find_root(doc)?);
+
+        if signodes.is_empty() {
+            return Err(XmlSecError::NodeNotFound);
+        }
+
+        let reports = signodes.into_iter()
+            .map(|signode| {
+                let (valid, error) = match self.verify_node_raw(signode)
+                {
+                    Ok(valid) => (valid, None),
+                    Err(err)  => (false, Some(err)),
+                };
+
+                XmlSecSignatureReport {
+                    xpath: node_path(signode),
+                    valid,
+                    error,
+                    report: self.verification_report(),
+                }
+            })
+            .collect();
+
+        Ok(reports)
+    }
+
+    /// Sets the point in time at which certificates found in `<ds:X509Data/>` get validated, which allows checking
+    /// archived signatures as of their signing date, long after their certificates expired. `None` validates them as
+    /// of now, which is the default. Times not past the unix epoch are refused, as xmlsec takes zero to mean now.
+    pub fn set_certs_verification_time(&mut self, time: Option<SystemTime>) -> XmlSecResult<()>
+    {
+        let secs = match time
+        {
+            Some(t) => match t.duration_since(UNIX_EPOCH)
+            {
+                Ok(since) if since.as_secs() > 0 => since.as_secs() as bindings::time_t,
+                _ => return Err("Verification time must be past the unix epoch".into()),
+            },
+
+            None => 0,
+        };
+
+        unsafe { (*self.ctx).keyInfoReadCtx.certsVerificationTime = secs };
+
+        Ok(())
+    }
+
+    /// Sets the maximum depth of the certificate chain built when validating certificates found in `<ds:X509Data/>`.
+    /// Depths beyond what xmlsec can hold, `i32::MAX`, are capped to it.
+    pub fn set_certs_verification_depth(&mut self, depth: u32)
+    {
+        let depth = i32::try_from(depth).unwrap_or(i32::MAX);
+
+        unsafe { (*self.ctx).keyInfoReadCtx.certsVerificationDepth = depth };
+    }
+
+    /// Enables or disables one of the [`XmlSecX509Flag`][flag] options driving how `<ds:X509Data/>` gets processed
+    /// while reading the `<ds:KeyInfo/>` of the signature.
+    ///
+    /// [flag]: enum.XmlSecX509Flag.html
+    pub fn set_x509_flag(&mut self, flag: XmlSecX509Flag, enable: bool)
+    {
+        unsafe {
+            if enable {
+                (*self.ctx).keyInfoReadCtx.flags |= flag as u32;
+            } else {
+                (*self.ctx).keyInfoReadCtx.flags &= ! (flag as u32);
+            }
+        }
+    }
+
+    /// Sets a resolver serving the data of references pointing outside the document, as found in detached
+    /// signatures. It is handed the URI of each such reference and returns the bytes it points at, or `None` when
+    /// unknown, in which case processing fails. While set, no other means of fetching URIs is attempted, hence
+    /// neither network nor filesystem get accessed by xmlsec on behalf of this context.
+    ///
+    /// Being called from within xmlsec, the resolver should not panic. Should it do so anyway, the panic is caught
+    /// and the URI treated as unknown.
+    pub fn set_uri_resolver<F>(&mut self, resolver: F)
+        where F: Fn(&str) -> Option<Vec<u8>> + 'static
+    {
+        self.resolver = Some(Rc::new(resolver));
+    }
+
+    /// Restricts the signature methods accepted in `<ds:SignedInfo/>` to the allowed ones. Until a first method gets
+    /// allowed with this, any known one is accepted. A signature using any other method fails verification with
+    /// [`XmlSecError::NotAllowed`][notallowed].
+    ///
+    /// xmlsec keeps a single list for all transforms of `<ds:SignedInfo/>`, hence this restricts its canonicalization
+    /// method as well: the ones to accept have to be allowed too with [`allow_canonicalization`][allowc14n], or every
+    /// signature gets rejected.
+    ///
+    /// [notallowed]: enum.XmlSecError.html#variant.NotAllowed
+    /// [allowc14n]: #method.allow_canonicalization
+    pub fn allow_signature_method<M>(&mut self, method: M) -> XmlSecResult<()>
+        where M: TryInto<XmlSecSignatureAlgorithm>, XmlSecError: From<M::Error>
+    {
+        let method = method.try_into()?;
+
+        self.enable_signature_transform(method.to_method())
+    }
+
+    /// Restricts the digest methods accepted in `<ds:Reference/>` to the allowed ones. As soon as any transform of
+    /// the references gets restricted, either with this, [`allow_canonicalization`][allowc14n],
+    /// [`allow_transform`][allowtr] or [`allow_enveloped_transform`][allowenv], only the allowed ones are accepted
+    /// there.
+    ///
+    /// [allowc14n]: #method.allow_canonicalization
+    /// [allowtr]: #method.allow_transform
+    /// [allowenv]: #method.allow_enveloped_transform
+    pub fn allow_digest_method<M>(&mut self, method: M) -> XmlSecResult<()>
+        where M: TryInto<XmlSecDigestAlgorithm>, XmlSecError: From<M::Error>
+    {
+        let method = method.try_into()?;
+
+        self.enable_reference_transform(method.to_method())
+    }
+
+    /// Restricts the canonicalization methods accepted, both for `<ds:SignedInfo/>` and as reference transforms, to
+    /// the allowed ones. Required alongside [`allow_signature_method`][allowsig] for `<ds:SignedInfo/>` to be
+    /// canonicalized at all.
+    ///
+    /// [allowsig]: #method.allow_signature_method
+    pub fn allow_canonicalization(&mut self, c14n: XmlSecCanonicalizationMethod) -> XmlSecResult<()>
+    {
+        self.enable_signature_transform(c14n.to_method())?;
+        self.enable_reference_transform(c14n.to_method())
+    }
+
+    /// Allows the given kind of transform within references restricted as described in
+    /// [`allow_digest_method`][allowdigest]. Only the kind matters, i.e. the expressions of XPath transforms or the
+    /// stylesheet of XSLT ones are not looked at.
+    ///
+    /// [allowdigest]: #method.allow_digest_method
+    pub fn allow_transform(&mut self, transform: &XmlSecTransform) -> XmlSecResult<()>
+    {
+        self.enable_reference_transform(transform.to_method())
+    }
+
+    /// Allows the enveloped signature transform within references restricted by
+    /// [`allow_digest_method`][allowdigest] or [`allow_canonicalization`][allowc14n].
+    ///
+    /// [allowdigest]: #method.allow_digest_method
+    /// [allowc14n]: #method.allow_canonicalization
+    pub fn allow_enveloped_transform(&mut self) -> XmlSecResult<()>
+    {
+        self.enable_reference_transform(unsafe { bindings::xmlSecTransformEnvelopedGetKlass() })
+    }
+
+    /// Restricts which kind of data `<ds:Reference/>` may point at. Any is allowed by default.
+    pub fn set_reference_uris(&mut self, uris: XmlSecReferenceUris)
+    {
+        unsafe { (*self.ctx).enabledReferenceUris = uris.to_uri_type() };
+    }
+
+    /// Sets whether to keep the data digested for each `<ds:Reference/>` of `<ds:SignedInfo/>` while verifying, so
+    /// that it can be inspected through the [`verification_report`][report]. Disabled by default.
+    ///
+    /// [report]: #method.verification_report
+    pub fn set_store_references(&mut self, store: bool)
+    {
+        unsafe {
+            if store {
+                (*self.ctx).flags |= bindings::XMLSEC_DSIG_FLAGS_STORE_SIGNEDINFO_REFERENCES;
+            } else {
+                (*self.ctx).flags &= ! bindings::XMLSEC_DSIG_FLAGS_STORE_SIGNEDINFO_REFERENCES;
+            }
+        }
+    }
+
+    /// Returns the nodes of `doc` referenced by the `<ds:SignedInfo/>` of the last successful verification, i.e. the
+    /// root element for same-document references (`URI=""`) and the element holding the ID for fragment ones
+    /// (`URI="#id"`), resolved just as xmlsec did. Processing only content reached from these nodes, rather than
+    /// looking it up again by name, defends against signature wrapping. Nothing is returned if the last verification
+    /// failed or was run on another document. External references are not part of the result, and neither is the
+    /// narrowing done by transforms such as XPath filters, for which the [pre-digest data][storerefs] is to be used.
+    ///
+    /// [storerefs]: #method.set_store_references
+    pub fn signed_nodes(&self, doc: &XmlDocument) -> Vec<XmlNode>
+    {
+        let anchor = match doc.get_root_element()
+        {
+            Some(root) => root,
+            None       => return Vec::new(),
+        };
+
+        self.signed_node_ptrs()
+            .into_iter()
+            .filter_map(|node| wrap_raw_node(&anchor, node))
+            .collect()
+    }
+
+    /// Tells whether `node` is one of the [`signed_nodes`][signed], or lies within one of them.
+    ///
+    /// [signed]: #method.signed_nodes
+    pub fn is_signed(&self, node: &XmlNode) -> bool
+    {
+        let signed = self.signed_node_ptrs();
+
+        let mut current = node.node_ptr() as *mut bindings::xmlNode;
+
+        while ! current.is_null()
+        {
+            if signed.contains(&current) {
+                return true;
+            }
+
+            current = unsafe { (*current).parent };
+        }
+
+        false
+    }
+
+    /// Same as [`is_signed`][issigned], failing with [`XmlSecError::NodeNotSigned`][notsigned] for nodes not covered
+    /// by the signature.
+    ///
+    /// [issigned]: #method.is_signed
+    /// [notsigned]: enum.XmlSecError.html#variant.NodeNotSigned
+    pub fn ensure_signed(&self, node: &XmlNode) -> XmlSecResult<()>
+    {
+        if self.is_signed(node) {
+            Ok(())
+        } else {
+            Err(XmlSecError::NodeNotSigned)
+        }
+    }
+
+    /// Tells whether the key used by the last verification is bound to a certificate that chains up to one of the
+    /// trusted certificates of the keys manager, taking into account the revocation lists loaded into it. This is
+    /// always `false` for contexts built without a keys manager or that have not verified anything yet, as well as
+    /// with crypto backends other than OpenSSL, for which certificates are not inspected by this wrapper.
+    pub fn certificate_verified(&self) -> bool
+    {
+        let mngr = match &self.mngr
+        {
+            Some(mngr) => mngr,
+            None       => return false,
+        };
+
+        // honour verification time and depth, but never any leniency on validation itself
+        let mut keyinfoctx = unsafe { (*self.ctx).keyInfoReadCtx };
+        keyinfoctx.flags &= ! bindings::XMLSEC_KEYINFO_FLAGS_X509DATA_DONT_VERIFY_CERTS;
+
+        backend::key_cert_verified(mngr, unsafe { (*self.ctx).signKey }, &mut keyinfoctx)
+    }
+
+    /// Reports on the outcome of the last verification run with this context: its overall status, the methods used,
+    /// the key it was checked with and each of the `<ds:Reference/>` it covered, along with their own status. Only
+    /// references that were actually processed show up, which allows auditing what a valid signature vouches for.
+    pub fn verification_report(&self) -> XmlSecVerificationReport
+    {
+        let ctx = self.ctx;
+
+        let mut references = Vec::new();
+
+        unsafe {
+            for list in [&mut (*ctx).signedInfoReferences, &mut (*ctx).manifestReferences]
+            {
+                for pos in 0..bindings::xmlSecPtrListGetSize(list)
+                {
+                    let refctx = bindings::xmlSecPtrListGetItem(list, pos) as *mut bindings::xmlSecDSigReferenceCtx;
+
+                    if ! refctx.is_null() {
+                        references.push(XmlSecReferenceReport::from_raw(refctx));
+                    }
+                }
+            }
+        }
+
+        let (key_name, certificate_subject) = unsafe {
+            let key = (*ctx).signKey;
+
+            if key.is_null() {
+                (None, None)
+            } else {
+                (xmlchar_to_string(bindings::xmlSecKeyGetName(key)), backend::key_cert_subject(key))
+            }
+        };
+
+        unsafe {
+            XmlSecVerificationReport {
+                status: XmlSecVerificationStatus::from_raw((*ctx).status),
+
+                signature_method:     XmlSecSignatureAlgorithm::from_method(id_of((*ctx).signMethod)),
+                signature_method_uri: href_of((*ctx).signMethod),
+                canonicalization_uri: href_of((*ctx).c14nMethod),
+
+                key_name,
+                certificate_subject,
+                certificate_verified: self.certificate_verified(),
+
+                references,
+            }
+        }
+    }
+
     /// # Safety
     ///
     /// Returns a raw pointer to the underlying xmlsec signature context. Beware that it is still managed by this
@@ -147,7 +690,7 @@ impl XmlSecSignatureContext
     {
         let ctx = self.ctx;  // keep a copy of the pointer
 
-        forget(self);  // release our copy of the pointer without deallocating it
+        forget(self);  // release our copy of the pointer without deallocating it, nor letting xmlsec shut down
 
         ctx  // return the only remaining copy
     }
@@ -156,10 +699,10 @@ impl XmlSecSignatureContext
 
 impl XmlSecSignatureContext
 {
-    fn key_is_set(&self) -> XmlSecResult<()>
+    fn key_is_available(&self) -> XmlSecResult<()>
     {
         unsafe {
-            if ! (*self.ctx).signKey.is_null() {
+            if ! (*self.ctx).signKey.is_null() || self.mngr.is_some() {
                 Ok(())
             } else {
                 Err(XmlSecError::KeyNotLoaded)
@@ -167,12 +710,89 @@ impl XmlSecSignatureContext
         }
     }
 
+    fn enable_signature_transform(&mut self, id: bindings::xmlSecTransformId) -> XmlSecResult<()>
+    {
+        let rc = unsafe { bindings::xmlSecDSigCtxEnableSignatureTransform(self.ctx, id) };
+
+        if rc < 0 {
+            return Err("Failed to allow signature transform".into());
+        }
+
+        Ok(())
+    }
+
+    fn enable_reference_transform(&mut self, id: bindings::xmlSecTransformId) -> XmlSecResult<()>
+    {
+        let rc = unsafe { bindings::xmlSecDSigCtxEnableReferenceTransform(self.ctx, id) };
+
+        if rc < 0 {
+            return Err("Failed to allow reference transform".into());
+        }
+
+        Ok(())
+    }
+
+    fn signed_node_ptrs(&self) -> Vec<*mut bindings::xmlNode>
+    {
+        let ctx = self.ctx;
+
+        let mut nodes = Vec::new();
+
+        unsafe {
+            if (*ctx).status != bindings::xmlSecDSigStatus_xmlSecDSigStatusSucceeded || (*ctx).signValueNode.is_null() {
+                return nodes;
+            }
+
+            let doc = (*(*ctx).signValueNode).doc;
+
+            for pos in 0..bindings::xmlSecPtrListGetSize(&mut (*ctx).signedInfoReferences)
+            {
+                let refctx = bindings::xmlSecPtrListGetItem(&mut (*ctx).signedInfoReferences, pos)
+                    as *mut bindings::xmlSecDSigReferenceCtx;
+
+                if refctx.is_null() || (*refctx).status != bindings::xmlSecDSigStatus_xmlSecDSigStatusSucceeded {
+                    continue;
+                }
+
+                if let Some(node) = xmlchar_to_string((*refctx).uri).and_then(|uri| resolve_uri(doc, &uri)) {
+                    nodes.push(node);
+                }
+            }
+        }
+
+        nodes
+    }
+
+    /// Readies the context for the next signature, see `reset_context` on what is kept.
+    fn reset(&self)
+    {
+        let ctx = self.ctx;
+
+        unsafe {
+            reset_context(&raw mut (*ctx).signKey, self.own_key, &raw mut (*ctx).keyInfoWriteCtx.keyReq, || {
+                #[cfg(xmlsec_dsig_ctx_reset)]
+                bindings::xmlSecDSigCtxReset(ctx);
+
+                #[cfg(not(xmlsec_dsig_ctx_reset))]
+                dsig_ctx_reset(ctx);
+            });
+        }
+    }
+
     fn sign_node_raw(&self, node: *mut bindings::xmlNode) -> XmlSecResult<()>
     {
+        crate::xmlsec::register_id_attributes(node);
+
+        clear_records();
+
+        self.reset();
+
+        let _resolver = ResolverGuard::activate(self.resolver.as_ref());
+
         let rc = unsafe { bindings::xmlSecDSigCtxSign(self.ctx, node) };
 
         if rc < 0 {
-            Err(XmlSecError::SigningError)
+            Err(library_error(XmlSecError::SigningError))
         } else {
             Ok(())
         }
@@ -180,20 +800,23 @@ impl XmlSecSignatureContext
 
     fn verify_node_raw(&self, node: *mut bindings::xmlNode) -> XmlSecResult<bool>
     {
+        crate::xmlsec::register_id_attributes(node);
+
+        clear_records();
+
+        self.reset();
+
+        let _resolver = ResolverGuard::activate(self.resolver.as_ref());
+
         let rc = unsafe { bindings::xmlSecDSigCtxVerify(self.ctx, node) };
 
         if rc < 0 {
-            return Err(XmlSecError::VerifyError);
+            return Err(policy_error(library_error(XmlSecError::VerifyError)));
         }
 
-        match unsafe { (*self.ctx).status }
-        {
-            bindings::xmlSecDSigStatus_xmlSecDSigStatusUnknown   => Ok(false),
-            bindings::xmlSecDSigStatus_xmlSecDSigStatusSucceeded => Ok(true),
-            bindings::xmlSecDSigStatus_xmlSecDSigStatusInvalid   => Ok(false),
+        let status = XmlSecVerificationStatus::from_raw(unsafe { (*self.ctx).status });
 
-            _ => panic!("Failed to interprete xmlSecDSigStatus code")
-        }
+        Ok(status == XmlSecVerificationStatus::Succeeded)
     }
 }
 
@@ -203,27 +826,24 @@ impl Drop for XmlSecSignatureContext
     fn drop(&mut self)
     {
         unsafe { bindings::xmlSecDSigCtxDestroy(self.ctx) };
+
+        release_xmlsec();
     }
 }
 
 
 fn find_root(doc: &XmlDocument) -> XmlSecResult<*mut bindings::xmlNode>
 {
-    if let Some(root) = doc.get_root_element()
-    {
-        let rawroot = root.node_ptr() as *mut bindings::xmlNode;
-        let signode = find_signode(rawroot)?;
-
-        Ok(signode)
-    } else {
-        Err(XmlSecError::RootNotFound)
-    }
+    doc.get_root_element()
+        .map(|root| root.node_ptr() as *mut bindings::xmlNode)
+        .ok_or(XmlSecError::RootNotFound)
 }
 
 
+/// Locates the first signature within a tree in document order.
 fn find_signode(tree: *mut bindings::xmlNode) -> XmlSecResult<*mut bindings::xmlNode>
 {
-    let signode = unsafe {bindings::xmlSecFindNode(
+    let signode = unsafe { bindings::xmlSecFindNode(
         tree,
         &bindings::xmlSecNodeSignature as *const c_uchar,
         &bindings::xmlSecDSigNs        as *const c_uchar,
@@ -235,3 +855,264 @@ fn find_signode(tree: *mut bindings::xmlNode) -> XmlSecResult<*mut bindings::xml
 
     Ok(signode)
 }
+
+
+/// Locates the signature for a given node: the node itself if it is a signature, otherwise one placed directly under
+/// it, or else the first found the same way down its children.
+fn find_subtree_signode(tree: *mut bindings::xmlNode) -> XmlSecResult<*mut bindings::xmlNode>
+{
+    if is_signode(tree) {
+        return Ok(tree);
+    }
+
+    let children = || {
+        std::iter::successors(unsafe { (*tree).children.as_mut() }, |n| unsafe { n.next.as_mut() })
+            .map(|n| n as *mut bindings::xmlNode)
+    };
+
+    if let Some(signode) = children().find(|n| is_signode(*n)) {
+        return Ok(signode);
+    }
+
+    children()
+        .find_map(|n| find_subtree_signode(n).ok())
+        .ok_or(XmlSecError::NodeNotFound)
+}
+
+
+/// Collects all signatures within a subtree, in document order.
+fn find_signodes(tree: *mut bindings::xmlNode) -> Vec<*mut bindings::xmlNode>
+{
+    let mut found = Vec::new();
+
+    if is_signode(tree) {
+        found.push(tree);
+    }
+
+    let children = std::iter::successors(unsafe { (*tree).children.as_mut() }, |n| unsafe { n.next.as_mut() });
+
+    for child in children {
+        found.extend(find_signodes(child));
+    }
+
+    found
+}
+
+
+fn node_depth(node: *mut bindings::xmlNode) -> usize
+{
+    std::iter::successors(unsafe { node.as_ref() }, |n| unsafe { n.parent.as_ref() })
+        .count()
+}
+
+
+fn node_path(node: *mut bindings::xmlNode) -> String
+{
+    unsafe {
+        let path = bindings::xmlGetNodePath(node);
+        let text = xmlchar_to_string(path).unwrap_or_default();
+
+        if let Some(free) = bindings::xmlFree {
+            free(path as *mut c_void);
+        }
+
+        text
+    }
+}
+
+
+fn is_signode(node: *mut bindings::xmlNode) -> bool
+{
+    unsafe {
+        (*node).type_ == bindings::xmlElementType_XML_ELEMENT_NODE && bindings::xmlSecCheckNodeName(
+            node,
+            &bindings::xmlSecNodeSignature as *const c_uchar,
+            &bindings::xmlSecDSigNs        as *const c_uchar,
+        ) != 0
+    }
+}
+
+
+impl XmlSecVerificationStatus
+{
+    fn from_raw(status: bindings::xmlSecDSigStatus) -> Self
+    {
+        match status
+        {
+            bindings::xmlSecDSigStatus_xmlSecDSigStatusUnknown   => Self::Unknown,
+            bindings::xmlSecDSigStatus_xmlSecDSigStatusSucceeded => Self::Succeeded,
+            bindings::xmlSecDSigStatus_xmlSecDSigStatusInvalid   => Self::Invalid,
+
+            _ => panic!("Failed to interprete xmlSecDSigStatus code")
+        }
+    }
+}
+
+
+impl XmlSecReferenceReport
+{
+    fn from_raw(refctx: *mut bindings::xmlSecDSigReferenceCtx) -> Self
+    {
+        let refctx = unsafe { &*refctx };
+
+        let origin = match refctx.origin
+        {
+            bindings::xmlSecDSigReferenceOrigin_xmlSecDSigReferenceOriginManifest => XmlSecReferenceOrigin::Manifest,
+
+            _ => XmlSecReferenceOrigin::SignedInfo,
+        };
+
+        Self {
+            origin,
+            status: XmlSecVerificationStatus::from_raw(refctx.status),
+
+            id:       xmlchar_to_string(refctx.id),
+            uri:      xmlchar_to_string(refctx.uri),
+            ref_type: xmlchar_to_string(refctx.type_),
+
+            digest_method:     XmlSecDigestAlgorithm::from_method(id_of(refctx.digestMethod)),
+            digest_method_uri: href_of(refctx.digestMethod),
+
+            pre_digest: membuf_data(refctx.preDigestMemBufMethod),
+        }
+    }
+}
+
+
+/// Stands in for `xmlSecDSigCtxReset`, which xmlsec only exports starting with 1.3, doing just what it does there.
+#[cfg(not(xmlsec_dsig_ctx_reset))]
+unsafe fn dsig_ctx_reset(ctx: bindings::xmlSecDSigCtxPtr)
+{
+    unsafe {
+        bindings::xmlSecTransformCtxReset(&mut (*ctx).transformCtx);
+        bindings::xmlSecKeyInfoCtxReset(&mut (*ctx).keyInfoReadCtx);
+        bindings::xmlSecKeyInfoCtxReset(&mut (*ctx).keyInfoWriteCtx);
+        bindings::xmlSecPtrListEmpty(&mut (*ctx).signedInfoReferences);
+        bindings::xmlSecPtrListEmpty(&mut (*ctx).manifestReferences);
+
+        if ! (*ctx).id.is_null()
+        {
+            if let Some(free) = bindings::xmlFree {
+                free((*ctx).id as *mut c_void);
+            }
+
+            (*ctx).id = null_mut();
+        }
+
+        if ! (*ctx).signKey.is_null()
+        {
+            bindings::xmlSecKeyDestroy((*ctx).signKey);
+
+            (*ctx).signKey = null_mut();
+        }
+
+        (*ctx).operation           = bindings::xmlSecTransformOperation_xmlSecTransformOperationNone;
+        (*ctx).result              = null_mut();
+        (*ctx).status              = bindings::xmlSecDSigStatus_xmlSecDSigStatusUnknown;
+        (*ctx).signMethod          = null_mut();
+        (*ctx).c14nMethod          = null_mut();
+        (*ctx).preSignMemBufMethod = null_mut();
+        (*ctx).signValueNode       = null_mut();
+    }
+}
+
+
+fn id_of(transform: bindings::xmlSecTransformPtr) -> bindings::xmlSecTransformId
+{
+    if transform.is_null() {
+        null()
+    } else {
+        unsafe { (*transform).id }
+    }
+}
+
+
+fn href_of(transform: bindings::xmlSecTransformPtr) -> Option<String>
+{
+    if transform.is_null() || unsafe { (*transform).id.is_null() } {
+        None
+    } else {
+        xmlchar_to_string(unsafe { (*(*transform).id).href })
+    }
+}
+
+
+fn xmlchar_to_string(ptr: *const c_uchar) -> Option<String>
+{
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(ptr as *const c_char) }.to_string_lossy().into_owned())
+    }
+}
+
+
+/// Resolves a same-document reference URI the way xmlsec does, for the forms it supports.
+fn resolve_uri(doc: *mut bindings::xmlDoc, uri: &str) -> Option<*mut bindings::xmlNode>
+{
+    let id = match uri
+    {
+        "" | "#xpointer(/)" => {
+            let root = unsafe { bindings::xmlDocGetRootElement(doc) };
+
+            return if root.is_null() { None } else { Some(root) };
+        },
+
+        _ if uri.starts_with("#xpointer(id(") && uri.ends_with("))") => {
+            uri["#xpointer(id(".len()..uri.len() - 2].trim_matches(|c| c == '\'' || c == '"')
+        },
+
+        _ if uri.starts_with('#') => &uri[1..],
+
+        _ => return None,
+    };
+
+    let cid = CString::new(id).ok()?;
+
+    let attr = unsafe { bindings::xmlGetID(doc, cid.as_ptr() as *const c_uchar) };
+
+    if attr.is_null() || unsafe { (*attr).parent.is_null() } {
+        None
+    } else {
+        Some(unsafe { (*attr).parent })
+    }
+}
+
+
+fn membuf_data(transform: bindings::xmlSecTransformPtr) -> Option<Vec<u8>>
+{
+    if transform.is_null() {
+        return None;
+    }
+
+    let buffer = unsafe { bindings::xmlSecTransformMemBufGetBuffer(transform) };
+
+    if buffer.is_null() {
+        return None;
+    }
+
+    let data = unsafe { bindings::xmlSecBufferGetData(buffer) };
+    let size = unsafe { bindings::xmlSecBufferGetSize(buffer) } as usize;
+
+    if data.is_null() {
+        Some(Vec::new())
+    } else {
+        Some(unsafe { std::slice::from_raw_parts(data, size) }.to_vec())
+    }
+}
+
+
+impl XmlSecReferenceUris
+{
+    fn to_uri_type(self) -> bindings::xmlSecTransformUriType
+    {
+        let samedoc = bindings::xmlSecTransformUriTypeEmpty | bindings::xmlSecTransformUriTypeSameDocument;
+
+        match self
+        {
+            Self::SameDocument => samedoc,
+            Self::Local        => samedoc | bindings::xmlSecTransformUriTypeLocal,
+            Self::Any          => bindings::xmlSecTransformUriTypeAny,
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//!
+//! Wrapper for XmlSec Encryption Context
+//!
+use crate::bindings;
+
+use crate::XmlSecKey;
+use crate::XmlSecKeysManager;
+use crate::XmlSecError;
+use crate::XmlSecResult;
+use crate::error::clear_records;
+use crate::error::library_error;
+use crate::xmlsec::acquire_xmlsec;
+use crate::xmlsec::release_xmlsec;
+use crate::xmlsec::reset_context;
+
+use crate::XmlNode;
+use crate::XmlDocument;
+
+use crate::exts::wrap_raw_node;
+use crate::exts::free_detached_nodes;
+
+use std::os::raw::c_uchar;
+use std::mem::forget;
+use std::ptr::null_mut;
+
+
+/// Encryption/decryption context
+pub struct XmlSecEncryptionContext
+{
+    ctx:     *mut bindings::xmlSecEncCtx,
+    mngr:    Option<XmlSecKeysManager>,
+    own_key: bool,
+}
+
+
+/// Kind of data that is being encrypted, as specified by the `Type` attribute of `<enc:EncryptedData/>`.
+#[allow(missing_docs)]
+pub enum XmlSecEncryptionType
+{
+    Element,
+    Content,
+}
+
+
+impl XmlSecEncryptionType
+{
+    /// Returns the resource pointer for the corresponding type URI
+    pub fn to_href(&self) -> *const c_uchar
+    {
+        match self
+        {
+            Self::Element => unsafe { &bindings::xmlSecTypeEncElement as *const c_uchar },
+            Self::Content => unsafe { &bindings::xmlSecTypeEncContent as *const c_uchar },
+        }
+    }
+}
+
+
+/// Outcome of a decryption, depending on the `Type` of the `<enc:EncryptedData/>` that was decrypted.
+#[derive(Debug)]
+pub enum XmlSecDecryptedData
+{
+    /// `Type="...#Element"`: the encrypted data has been replaced within the document by the decrypted element,
+    /// which is returned.
+    Element(XmlNode),
+
+    /// `Type="...#Content"`: the encrypted data has been replaced within the document by the decrypted content. The
+    /// node containing it is returned.
+    Content(XmlNode),
+
+    /// Any other type: the document has not been touched and the decrypted bytes are returned as they are.
+    Binary(Vec<u8>),
+}
+
+
+impl XmlSecEncryptionContext
+{
+    /// Builds a context, ensuring xmlsec is initialized.
+    pub fn new() -> Self
+    {
+        acquire_xmlsec();
+
+        let ctx = unsafe { bindings::xmlSecEncCtxCreate(null_mut()) };
+
+        if ctx.is_null() {
+            panic!("Failed to create enc context");
+        }
+
+        keep_replaced_nodes(ctx);
+
+        Self {ctx, mngr: None, own_key: false}
+    }
+
+    /// Builds a context that resolves keys through the given [`XmlSecKeysManager`][mngr] from the `<ds:KeyInfo/>`
+    /// being processed, unless one is explicitly set with [`insert_key`][inskey].
+    ///
+    /// [mngr]: struct.XmlSecKeysManager.html
+    /// [inskey]: #method.insert_key
+    pub fn with_keys_manager(mngr: &XmlSecKeysManager) -> Self
+    {
+        acquire_xmlsec();
+
+        let ctx = unsafe { bindings::xmlSecEncCtxCreate(mngr.as_ptr()) };
+
+        if ctx.is_null() {
+            panic!("Failed to create enc context");
+        }
+
+        keep_replaced_nodes(ctx);
+
+        Self {ctx, mngr: Some(mngr.clone()), own_key: false}
+    }
+
+    /// Sets the key to use for encryption or decryption. In case a key had already been set, the latter one gets
+    /// released in the optional return.
+    pub fn insert_key(&mut self, key: XmlSecKey) -> Option<XmlSecKey>
+    {
+        let mut old = None;
+
+        unsafe {
+            if ! (*self.ctx).encKey.is_null() {
+                old = Some(XmlSecKey::from_ptr((*self.ctx).encKey));
+            }
+
+            (*self.ctx).encKey = XmlSecKey::leak(key);
+        }
+
+        self.own_key = true;
+
+        old
+    }
+
+    /// Releases a currently set key returning `Some(key)` or None otherwise.
+    pub fn release_key(&mut self) -> Option<XmlSecKey>
+    {
+        unsafe {
+            if (*self.ctx).encKey.is_null() {
+                None
+            } else {
+                let key = XmlSecKey::from_ptr((*self.ctx).encKey);
+
+                (*self.ctx).encKey = null_mut();
+
+                self.own_key = false;
+
+                Some(key)
+            }
+        }
+    }
+
+    /// Encrypts `node` as a whole using the `<enc:EncryptedData/>` template given. The template is marked with
+    /// `Type="...#Element"` and then takes the place of `node` within its document, filled with the cipher data. In
+    /// case the template belongs to a different document, a copy of it is used instead and the original is left as is.
+    ///
+    /// The plaintext node is released once replaced. Handles to it or its children are left pointing at nothing, as
+    /// after [`free_subtree`][free], rather than at freed memory.
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set or the template is malformed.
+    ///
+    /// [free]: http://kwarc.github.io/rust-libxml/libxml/tree/node/struct.Node.html#method.free_subtree
+    pub fn encrypt_node(&self, template: &XmlNode, node: &XmlNode) -> XmlSecResult<()>
+    {
+        self.key_is_available()?;
+
+        let anchor = node.get_parent()
+            .unwrap_or_else(|| node.clone());
+
+        let tmpl = template.node_ptr() as bindings::xmlNodePtr;
+        let node = node.node_ptr() as bindings::xmlNodePtr;
+
+        self.encrypt_xml_raw(&anchor, tmpl, node, XmlSecEncryptionType::Element.to_href())
+    }
+
+    /// Encrypts the children of `node` using the `<enc:EncryptedData/>` template given. The template is marked with
+    /// `Type="...#Content"` and then replaces the content of `node`, which itself remains in place. Same as with
+    /// [`encrypt_node`][encnode], templates from other documents are copied over first.
+    ///
+    /// The plaintext children are released once replaced, leaving handles to them pointing at nothing.
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set or the template is malformed.
+    ///
+    /// [encnode]: struct.XmlSecEncryptionContext.html#method.encrypt_node
+    pub fn encrypt_content(&self, template: &XmlNode, node: &XmlNode) -> XmlSecResult<()>
+    {
+        self.key_is_available()?;
+
+        let tmpl = template.node_ptr() as bindings::xmlNodePtr;
+        let raw  = node.node_ptr() as bindings::xmlNodePtr;
+
+        self.encrypt_xml_raw(node, tmpl, raw, XmlSecEncryptionType::Content.to_href())
+    }
+
+    /// Encrypts an arbitrary buffer of bytes, writing the result into the `<enc:CipherValue/>` of the template given.
+    /// The template is left wherever it was, so it may be a standalone document or part of a larger one.
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set or the template is malformed.
+    pub fn encrypt_binary(&self, template: &XmlNode, data: &[u8]) -> XmlSecResult<()>
+    {
+        self.key_is_available()?;
+
+        let tmpl = template.node_ptr() as bindings::xmlNodePtr;
+
+        clear_records();
+
+        self.reset();
+
+        let rc = unsafe { bindings::xmlSecEncCtxBinaryEncrypt(
+            self.ctx,
+            tmpl,
+            data.as_ptr(),
+            data.len() as u32
+        ) };
+
+        if rc < 0 {
+            return Err(library_error(XmlSecError::EncryptionError));
+        }
+
+        Ok(())
+    }
+
+    /// Takes an `<enc:EncryptedData/>` node and attempts to decrypt it. See [`XmlSecDecryptedData`][decdata] for what
+    /// is done with the result depending on its type.
+    ///
+    /// Once replaced by the decrypted data, the `<enc:EncryptedData/>` node is released, leaving handles to it or its
+    /// children pointing at nothing.
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set, the node is not part of a document tree, or the encrypted data is malformed
+    /// or could not be decrypted.
+    ///
+    /// [decdata]: enum.XmlSecDecryptedData.html
+    pub fn decrypt_node(&self, node: &XmlNode) -> XmlSecResult<XmlSecDecryptedData>
+    {
+        self.key_is_available()?;
+
+        let anchor = node.get_parent()
+            .unwrap_or_else(|| node.clone());
+
+        self.decrypt_node_raw(&anchor, node.node_ptr() as bindings::xmlNodePtr)
+    }
+
+    /// Takes a [`XmlDocument`][xmldoc] and attempts to decrypt the first `<enc:EncryptedData/>` found within. See
+    /// [`XmlSecDecryptedData`][decdata] for what is done with the result depending on its type.
+    ///
+    /// Same as with [`decrypt_node`][decnode], the replaced encrypted data is released.
+    ///
+    /// # Errors
+    ///
+    /// If key has not been previously set, no encrypted data could be found or it could not be decrypted.
+    ///
+    /// [xmldoc]: http://kwarc.github.io/rust-libxml/libxml/tree/document/struct.Document.html
+    /// [decdata]: enum.XmlSecDecryptedData.html
+    /// [decnode]: struct.XmlSecEncryptionContext.html#method.decrypt_node
+    pub fn decrypt_document(&self, doc: &XmlDocument) -> XmlSecResult<XmlSecDecryptedData>
+    {
+        self.key_is_available()?;
+
+        let root = doc.get_root_element()
+            .ok_or(XmlSecError::RootNotFound)?;
+
+        let encnode = find_encnode(root.node_ptr() as bindings::xmlNodePtr)?;
+
+        // the root itself may get replaced, so it can only serve as anchor if it is not the one being decrypted
+        let anchor = if encnode as usize == root.to_hashable() {
+            root.get_parent().ok_or(XmlSecError::RootNotFound)?
+        } else {
+            root
+        };
+
+        self.decrypt_node_raw(&anchor, encnode)
+    }
+
+    /// # Safety
+    ///
+    /// Returns a raw pointer to the underlying xmlsec encryption context. Beware that it is still managed by this
+    /// wrapping object and will be deallocated once `self` gets dropped.
+    pub unsafe fn as_ptr(&self) -> *mut bindings::xmlSecEncCtx
+    {
+        self.ctx
+    }
+
+    /// # Safety
+    ///
+    /// Returns a raw pointer to the underlying xmlsec encryption context. Beware that it will be forgotten by this
+    /// wrapping object and *must* be deallocated manually by the callee.
+    pub unsafe fn into_ptr(self) -> *mut bindings::xmlSecEncCtx
+    {
+        let ctx = self.ctx;  // keep a copy of the pointer
+
+        forget(self);  // release our copy of the pointer without deallocating it, nor letting xmlsec shut down
+
+        ctx  // return the only remaining copy
+    }
+}
+
+
+impl XmlSecEncryptionContext
+{
+    /// Readies the context for the next operation, see `reset_context` on what is kept.
+    fn reset(&self)
+    {
+        let ctx = self.ctx;
+
+        unsafe {
+            reset_context(&raw mut (*ctx).encKey, self.own_key, &raw mut (*ctx).keyInfoWriteCtx.keyReq, || {
+                bindings::xmlSecEncCtxReset(ctx);
+            });
+        }
+    }
+
+    /// Releases the nodes replaced by the last operation, which xmlsec hands over instead of freeing them, so that
+    /// handles to them are neutralized rather than left dangling.
+    fn free_replaced_nodes(&self, anchor: &XmlNode)
+    {
+        let replaced = unsafe { (*self.ctx).replacedNodeList };
+
+        unsafe { (*self.ctx).replacedNodeList = null_mut() };
+
+        free_detached_nodes(anchor, replaced);
+    }
+
+    fn key_is_available(&self) -> XmlSecResult<()>
+    {
+        unsafe {
+            if ! (*self.ctx).encKey.is_null() || self.mngr.is_some() {
+                Ok(())
+            } else {
+                Err(XmlSecError::KeyNotLoaded)
+            }
+        }
+    }
+
+    fn encrypt_xml_raw(
+        &self,
+        anchor:  &XmlNode,
+        tmpl:    *mut bindings::xmlNode,
+        node:    *mut bindings::xmlNode,
+        enctype: *const c_uchar,
+    ) -> XmlSecResult<()>
+    {
+        // the template ends up within the document of the node, hence it must not be shared with another one
+        let copied = unsafe { (*tmpl).doc != (*node).doc };
+
+        let tmpl = if copied {
+            unsafe { bindings::xmlDocCopyNode(tmpl, (*node).doc, 1) }
+        } else {
+            tmpl
+        };
+
+        if tmpl.is_null() {
+            return Err(XmlSecError::EncryptionError);
+        }
+
+        unsafe { bindings::xmlSetProp(tmpl, &bindings::xmlSecAttrType as *const c_uchar, enctype) };
+
+        crate::xmlsec::register_id_attributes(node);
+
+        clear_records();
+
+        self.reset();
+
+        let rc = unsafe { bindings::xmlSecEncCtxXmlEncrypt(self.ctx, tmpl, node) };
+
+        self.free_replaced_nodes(anchor);
+
+        if rc < 0
+        {
+            if copied {
+                unsafe { bindings::xmlFreeNode(tmpl) };
+            }
+
+            return Err(library_error(XmlSecError::EncryptionError));
+        }
+
+        Ok(())
+    }
+
+    fn decrypt_node_raw(&self, anchor: &XmlNode, node: *mut bindings::xmlNode) -> XmlSecResult<XmlSecDecryptedData>
+    {
+        // keep track of where the encrypted data was, since it may be gone once decrypted
+        let parent = unsafe { (*node).parent };
+        let prev   = unsafe { (*node).prev };
+
+        if parent.is_null() {
+            return Err("Encrypted data has to be part of a document tree to be decrypted".into());
+        }
+
+        crate::xmlsec::register_id_attributes(node);
+
+        clear_records();
+
+        self.reset();
+
+        let rc = unsafe { bindings::xmlSecEncCtxDecrypt(self.ctx, node) };
+
+        self.free_replaced_nodes(anchor);
+
+        if rc < 0 {
+            return Err(library_error(XmlSecError::DecryptionError));
+        }
+
+        unsafe {
+            if (*self.ctx).resultReplaced == 0
+            {
+                let result = (*self.ctx).result;
+
+                let data = bindings::xmlSecBufferGetData(result);
+                let size = bindings::xmlSecBufferGetSize(result) as usize;
+
+                if data.is_null() {
+                    return Ok(XmlSecDecryptedData::Binary(Vec::new()));
+                }
+
+                return Ok(XmlSecDecryptedData::Binary(std::slice::from_raw_parts(data, size).to_vec()));
+            }
+
+            if bindings::xmlStrEqual((*self.ctx).type_, &bindings::xmlSecTypeEncContent as *const c_uchar) != 0
+            {
+                let content = wrap_raw_node(anchor, parent)
+                    .ok_or(XmlSecError::NodeNotFound)?;
+
+                return Ok(XmlSecDecryptedData::Content(content));
+            }
+
+            // the decrypted element took the place of the encrypted data right after its previous sibling
+            let mut decrypted = if prev.is_null() { (*parent).children } else { (*prev).next };
+
+            while ! decrypted.is_null() && (*decrypted).type_ != bindings::xmlElementType_XML_ELEMENT_NODE {
+                decrypted = (*decrypted).next;
+            }
+
+            let element = wrap_raw_node(anchor, decrypted)
+                .ok_or(XmlSecError::NodeNotFound)?;
+
+            Ok(XmlSecDecryptedData::Element(element))
+        }
+    }
+}
+
+
+impl Drop for XmlSecEncryptionContext
+{
+    fn drop(&mut self)
+    {
+        unsafe { bindings::xmlSecEncCtxDestroy(self.ctx) };
+
+        release_xmlsec();
+    }
+}
+
+
+/// Makes xmlsec hand over the nodes it replaces, instead of freeing them while wrapped nodes may point at them.
+fn keep_replaced_nodes(ctx: *mut bindings::xmlSecEncCtx)
+{
+    unsafe { (*ctx).flags |= bindings::XMLSEC_ENC_RETURN_REPLACED_NODE };
+}
+
+
+fn find_encnode(tree: *mut bindings::xmlNode) -> XmlSecResult<*mut bindings::xmlNode>
+{
+    let encnode = unsafe { bindings::xmlSecFindNode(
+        tree,
+        &bindings::xmlSecNodeEncryptedData as *const c_uchar,
+        &bindings::xmlSecEncNs             as *const c_uchar,
+    ) };
+
+    if encnode.is_null() {
+        return Err(XmlSecError::NodeNotFound);
+    }
+
+    Ok(encnode)
+}
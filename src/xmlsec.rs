@@ -2,10 +2,24 @@
 //! Central XmlSec1 Context
 //!
 use crate::bindings;
+use crate::crypto::backend;
 
 use crate::lazy_static;
 
+use crate::XmlSecError;
+use crate::XmlSecErrorRecord;
+use crate::XmlSecResult;
+use crate::error::clear_records;
+use crate::error::library_error;
+use crate::error::set_error_callback;
+
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::os::raw::c_uchar;
 use std::ptr::null;
+use std::ptr::null_mut;
+use std::sync::Arc;
 use std::sync::Mutex;
 
 
@@ -14,6 +28,194 @@ lazy_static! {
 }
 
 
+/// Callback receiving every error reported by xmlsec, as configured through
+/// [`XmlSecConfig::error_callback`][callback]. It must not panic, since it is called from within xmlsec.
+///
+/// [callback]: struct.XmlSecConfig.html#method.error_callback
+pub type XmlSecErrorCallback = Arc<dyn Fn(&XmlSecErrorRecord) + Send + Sync>;
+
+
+/// Explicit initialization of the library. Using this wrapper without it still initializes xmlsec lazily, with the
+/// default configuration, but then failures to do so panic and xmlsec never gets shut down.
+pub struct XmlSec;
+
+
+/// Configuration of the library for [`XmlSec::init`][init]. The default one matches what is used for lazy
+/// initialization.
+///
+/// [init]: struct.XmlSec.html#method.init
+#[derive(Clone, Default)]
+pub struct XmlSecConfig
+{
+    crypto:         Option<String>,
+    crypto_config:  Option<String>,
+    error_callback: Option<XmlSecErrorCallback>,
+    id_attributes:  Vec<String>,
+}
+
+
+/// Versions of the libraries behind this wrapper, as returned by [`XmlSec::versions`][versions].
+///
+/// [versions]: struct.XmlSec.html#method.versions
+#[derive(Clone, Debug)]
+pub struct XmlSecVersions
+{
+    /// Version of xmlsec the wrapper was built against.
+    pub xmlsec: String,
+    /// Whether the xmlsec library loaded at runtime is ABI compatible with the one built against.
+    pub xmlsec_compatible: bool,
+    /// Version of the libxml2 library loaded at runtime.
+    pub libxml2: String,
+    /// Version of the crypto library loaded at runtime (OpenSSL, NSS, GnuTLS or libgcrypt), as reported by it.
+    pub crypto: Option<String>,
+}
+
+
+/// Keeps xmlsec initialized for as long as it lives, shutting it down in order once dropped. Contexts, keys and keys
+/// managers of this wrapper still alive by then defer the shut down until the last of them is gone, meanwhile xmlsec
+/// cannot be initialized again.
+#[must_use = "xmlsec gets shut down as soon as the guard is dropped"]
+pub struct XmlSecGuard
+{
+    _private: (),
+}
+
+
+impl XmlSec
+{
+    /// Initializes xmlsec and its crypto backend with the given configuration, failing if that does not succeed or if
+    /// it is already initialized, be it explicitly or lazily by having used the wrapper beforehand.
+    pub fn init(config: XmlSecConfig) -> XmlSecResult<XmlSecGuard>
+    {
+        let mut inner = XMLSEC.lock()
+            .expect("Unable to lock global xmlsec initalization wrapper");
+
+        let refusal = match inner.as_ref()
+        {
+            Some(ctx) if ctx.shutdown_pending => Some("XmlSec is still in use since its previous initialization"),
+            Some(_)                           => Some("XmlSec has already been initialized"),
+            None                              => None,
+        };
+
+        if let Some(reason) = refusal {
+            return Err(reason.into());
+        }
+
+        set_error_callback(config.error_callback.clone());  // already for errors raised while initializing
+
+        match XmlSecContext::init(&config)
+        {
+            Ok(ctx) => *inner = Some(ctx),
+
+            Err(err) => {
+                set_error_callback(None);
+
+                return Err(err);
+            }
+        }
+
+        Ok(XmlSecGuard {_private: ()})
+    }
+
+    /// Reports the versions of xmlsec, libxml2 and the crypto library in use. Does not initialize xmlsec.
+    pub fn versions() -> XmlSecVersions
+    {
+        let xmlsec = CStr::from_bytes_with_nul(bindings::XMLSEC_VERSION)
+            .expect("Malformed xmlsec version")
+            .to_string_lossy()
+            .into_owned();
+
+        XmlSecVersions {
+            xmlsec,
+            xmlsec_compatible: xmlsec_compatible(),
+            libxml2:           libxml2_version(),
+            crypto:            backend::crypto_version(),
+        }
+    }
+
+    /// Fails if the xmlsec library loaded at runtime is not ABI compatible with the one this wrapper was built
+    /// against, so as to bail out at startup rather than on first use. Does not initialize xmlsec.
+    pub fn check_version() -> XmlSecResult<()>
+    {
+        clear_records();
+
+        if ! xmlsec_compatible()
+        {
+            return Err(library_error(format!(
+                "Runtime xmlsec library is not compatible with version {} built against",
+                Self::versions().xmlsec
+            ).into()));
+        }
+
+        Ok(())
+    }
+}
+
+
+impl XmlSecConfig
+{
+    /// Builds the default configuration.
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Name of the crypto backend ("openssl", "nss", "gnutls" or "gcrypt"), which has to match the one selected at
+    /// build time.
+    pub fn crypto_backend(mut self, name: &str) -> Self
+    {
+        self.crypto = Some(name.to_owned());
+        self
+    }
+
+    /// Configuration handed over to the crypto backend on initialization, usually a directory: the one of default
+    /// trusted certificates for OpenSSL and the certificates database for NSS.
+    pub fn crypto_config(mut self, config: &str) -> Self
+    {
+        self.crypto_config = Some(config.to_owned());
+        self
+    }
+
+    /// Callback receiving every error reported by xmlsec, for instance for logging. The errors still get attached to
+    /// the [`XmlSecError::Library`][liberr] of the failed operation.
+    ///
+    /// [liberr]: enum.XmlSecError.html#variant.Library
+    pub fn error_callback<F>(mut self, callback: F) -> Self
+        where F: Fn(&XmlSecErrorRecord) + Send + Sync + 'static
+    {
+        self.error_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Name of an attribute to be taken as ID, such as `ID` for SAML, on any element of the documents signed,
+    /// verified, encrypted or decrypted, sparing calls to [`specify_idattr`][idattr] for each of them. May be given
+    /// several times.
+    ///
+    /// [idattr]: trait.XmlSecDocumentExt.html#tymethod.specify_idattr
+    pub fn id_attribute(mut self, name: &str) -> Self
+    {
+        self.id_attributes.push(name.to_owned());
+        self
+    }
+}
+
+
+impl Drop for XmlSecGuard
+{
+    fn drop(&mut self)
+    {
+        let mut inner = XMLSEC.lock()
+            .expect("Unable to lock global xmlsec initalization wrapper");
+
+        match inner.as_mut()
+        {
+            Some(ctx) if ctx.users > 0 => ctx.shutdown_pending = true,  // left to the last user
+            _                          => shutdown(&mut inner),
+        }
+    }
+}
+
+
 pub fn guarantee_xmlsec_init()
 {
     let mut inner = XMLSEC.lock()
@@ -25,6 +227,136 @@ pub fn guarantee_xmlsec_init()
 }
 
 
+/// Ensures xmlsec is initialized and keeps it from being shut down until a matching call to `release_xmlsec`. To be
+/// called by every object holding onto xmlsec resources when built.
+pub(crate) fn acquire_xmlsec()
+{
+    let mut inner = XMLSEC.lock()
+        .expect("Unable to lock global xmlsec initalization wrapper");
+
+    inner.get_or_insert_with(XmlSecContext::new)
+        .users += 1;
+}
+
+
+/// Counterpart of `acquire_xmlsec`, to be called once the resources are freed. Completes a shut down requested by
+/// dropping the [`XmlSecGuard`][guard] in the meantime if this was the last user.
+///
+/// [guard]: struct.XmlSecGuard.html
+pub(crate) fn release_xmlsec()
+{
+    // called from drop, where panicking while unwinding would abort
+    let mut inner = XMLSEC.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    if let Some(ctx) = inner.as_mut()
+    {
+        ctx.users = ctx.users.saturating_sub(1);
+
+        if ctx.users == 0 && ctx.shutdown_pending {
+            shutdown(&mut inner);
+        }
+    }
+}
+
+
+fn shutdown(inner: &mut Option<XmlSecContext>)
+{
+    set_error_callback(None);
+
+    inner.take();  // shuts it down
+}
+
+
+/// Declares the ID attributes configured on initialization on the whole document the node belongs to.
+pub(crate) fn register_id_attributes(node: *mut bindings::xmlNode)
+{
+    let inner = XMLSEC.lock()
+        .expect("Unable to lock global xmlsec initalization wrapper");
+
+    let ctx = match inner.as_ref()
+    {
+        Some(ctx) if ! ctx.id_attributes.is_empty() && ! node.is_null() => ctx,
+        _                                                             => return,
+    };
+
+    let mut ids = ctx.id_attributes.iter()
+        .map(|name| name.as_ptr() as *const c_uchar)
+        .collect::<Vec<_>>();
+
+    ids.push(null());  // null terminated list
+
+    unsafe {
+        let doc = (*node).doc;
+
+        bindings::xmlSecAddIDs(doc, bindings::xmlDocGetRootElement(doc), ids.as_mut_ptr());
+    }
+}
+
+
+/// Resets a signature or encryption context through `reset`, so that it may be used once more. xmlsec contexts are
+/// meant for a single operation, hence whatever a previous one left behind is cleared, while the configuration is
+/// kept. So is the key behind `key` when owned by the wrapper, whereas one resolved through the keys manager is
+/// dropped, so that each operation resolves its own. The requirements on the key written to `<ds:KeyInfo/>`,
+/// behind `write_req`, are configured once by xmlsec not to write private keys, which the reset would clear as well,
+/// hence they are restored.
+///
+/// # Safety
+///
+/// Both pointers have to be fields of the context `reset` is called upon.
+pub(crate) unsafe fn reset_context<F>(
+    key:       *mut bindings::xmlSecKeyPtr,
+    own_key:   bool,
+    write_req: *mut bindings::xmlSecKeyReq,
+    reset:     F,
+) where F: FnOnce()
+{
+    unsafe {
+        let kept = if own_key { key.replace(null_mut()) } else { null_mut() };  // spare it from being destroyed
+
+        let req = write_req.read();
+
+        reset();
+
+        (*write_req).keyId       = req.keyId;
+        (*write_req).keyType     = req.keyType;
+        (*write_req).keyUsage    = req.keyUsage;
+        (*write_req).keyBitsSize = req.keyBitsSize;
+
+        *key = kept;
+    }
+}
+
+
+/// Checks the xmlsec library loaded at runtime against the version of the headers built against.
+fn xmlsec_compatible() -> bool
+{
+    let rc = unsafe { bindings::xmlSecCheckVersionExt(
+        bindings::XMLSEC_VERSION_MAJOR as i32,
+        bindings::XMLSEC_VERSION_MINOR as i32,
+        bindings::XMLSEC_VERSION_SUBMINOR as i32,
+        bindings::xmlSecCheckVersionMode_xmlSecCheckVersionABICompatible
+    ) };
+
+    rc == 1
+}
+
+
+/// Version of libxml2 as dotted string, which it reports as a single number such as "20914" for 2.9.14.
+fn libxml2_version() -> String
+{
+    let version = unsafe { CStr::from_ptr(*bindings::__xmlParserVersion()) }
+        .to_string_lossy()
+        .into_owned();
+
+    match version.parse::<u32>()
+    {
+        Ok(number) => format!("{}.{}.{}", number / 10000, number / 100 % 100, number % 100),
+        Err(_)     => version,
+    }
+}
+
+
 /// XmlSec Global Context
 ///
 /// This object initializes the underlying xmlsec global state and cleans it
@@ -32,19 +364,56 @@ pub fn guarantee_xmlsec_init()
 /// require the context to be initialized. See [`globals`][globals].
 ///
 /// [globals]: globals
-struct XmlSecContext {}
+struct XmlSecContext
+{
+    id_attributes: Vec<CString>,
+
+    users:            usize,
+    shutdown_pending: bool,
+}
 
 
 impl XmlSecContext
 {
-    /// Runs xmlsec initialization and returns instance of itself.
+    /// Runs xmlsec initialization with the default configuration and returns instance of itself, panicking on failure.
     pub fn new() -> Self
     {
-        init_xmlsec();
-        init_crypto_app();
-        init_crypto();
+        Self::init(&XmlSecConfig::default())
+            .expect("XmlSec failed initialization")
+    }
+
+    /// Runs xmlsec initialization, undoing whatever succeeded if any of its steps fails.
+    pub fn init(config: &XmlSecConfig) -> XmlSecResult<Self>
+    {
+        let id_attributes = config.id_attributes.iter()
+            .map(|name| CString::new(name.as_str()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| XmlSecError::Str("ID attribute names must not contain null bytes".to_owned()))?;
+
+        clear_records();
+
+        init_xmlsec()?;
+        init_errors();  // collect errors of the crypto initialization too
 
-        Self {}
+        if let Err(err) = init_crypto_app(config.crypto.as_deref(), config.crypto_config.as_deref())
+        {
+            cleanup_xmlsec();
+
+            return Err(err);
+        }
+
+        if let Err(err) = init_crypto()
+        {
+            cleanup_crypto_app();
+            cleanup_xmlsec();
+
+            return Err(err);
+        }
+
+        init_errors();  // the crypto backend installs its own callback on init
+        init_io();
+
+        Ok(Self {id_attributes, users: 0, shutdown_pending: false})
     }
 }
 
@@ -61,62 +430,83 @@ impl Drop for XmlSecContext
 
 
 /// Init xmlsec library
-fn init_xmlsec()
+fn init_xmlsec() -> XmlSecResult<()>
 {
     let rc = unsafe { bindings::xmlSecInit() };
 
     if rc < 0 {
-        panic!("XmlSec failed initialization");
+        return Err("XmlSec failed initialization".into());
     }
+
+    Ok(())
 }
 
 
-/// Load default crypto engine if we are supporting dynamic loading for
-/// xmlsec-crypto libraries. Use the crypto library name ("openssl",
-/// "nss", etc.) to load corresponding xmlsec-crypto library.
-fn init_crypto_app()
+/// Route xmlsec errors into per-thread records attached to the errors we return
+fn init_errors()
 {
-    // if bindings::XMLSEC_CRYPTO_DYNAMIC_LOADING
-    // {
-    //     let rc = unsafe { bindings::xmlSecCryptoDLLoadLibrary(0) };
+    unsafe { bindings::xmlSecErrorsSetCallback(Some(crate::error::collect_error)) };
+}
+
 
-    //     if rc < 0 {
-    //         panic!("XmlSec failed while loading default crypto backend. \
-    //                 Make sure that you have it installed and check shread libraries path");
-    //     }
-    // }
+/// Serve external URIs through the resolvers set on contexts
+fn init_io()
+{
+    crate::io::register_callbacks();
+}
 
-    let rc = unsafe { bindings::xmlSecOpenSSLAppInit(null()) };
+
+/// Init crypto library, after checking the requested backend is the one built against
+fn init_crypto_app(crypto: Option<&str>, config: Option<&str>) -> XmlSecResult<()>
+{
+    if let Some(name) = crypto.filter(|name| ! name.eq_ignore_ascii_case(backend::NAME))
+    {
+        return Err(format!("Crypto backend {} requested, while built against {}", name, backend::NAME).into());
+    }
+
+    let cconfig = config.map(CString::new)
+        .transpose()
+        .map_err(|_| XmlSecError::Str("Crypto configuration must not contain null bytes".to_owned()))?;
+
+    let cconfig_ptr = cconfig.as_ref()
+        .map(|cconfig| cconfig.as_ptr() as *const c_char)
+        .unwrap_or(null());
+
+    let rc = unsafe { backend::app_init(cconfig_ptr) };
 
     if rc < 0 {
-        panic!("XmlSec failed to init crypto backend")
+        return Err(library_error("XmlSec failed to init crypto backend".into()));
     }
+
+    Ok(())
 }
 
 
 /// Init xmlsec-crypto library
-fn init_crypto()
+fn init_crypto() -> XmlSecResult<()>
 {
-    let rc = unsafe { bindings::xmlSecOpenSSLInit() };
+    let rc = unsafe { backend::init() };
 
     if rc < 0 {
-        panic!("XmlSec failed while loading default crypto backend. \
-               Make sure that you have it installed and check shread libraries path");
+        return Err(library_error("XmlSec failed while loading default crypto backend. \
+                                  Make sure that you have it installed and check shread libraries path".into()));
     }
+
+    Ok(())
 }
 
 
 /// Shutdown xmlsec-crypto library
 fn cleanup_crypto()
 {
-    unsafe { bindings::xmlSecOpenSSLShutdown() };
+    unsafe { backend::shutdown() };
 }
 
 
 /// Shutdown crypto library
 fn cleanup_crypto_app()
 {
-    unsafe { bindings::xmlSecOpenSSLAppShutdown() };
+    unsafe { backend::app_shutdown() };
 }
 
 
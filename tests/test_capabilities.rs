@@ -0,0 +1,103 @@
+//!
+//! Unit Tests for Runtime Capability Discovery
+//!
+use xmlsec::XmlSec;
+use xmlsec::XmlSecSignatureAlgorithm;
+use xmlsec::XmlSecDigestAlgorithm;
+use xmlsec::XmlSecBlockCipher;
+use xmlsec::XmlSecKeyTransport;
+use xmlsec::XmlSecReferenceBuilder;
+use xmlsec::XmlSecTemplateBuilder;
+use xmlsec::XmlSecDocumentTemplating;
+
+use libxml::parser::Parser as XmlParser;
+
+
+#[test]
+fn test_supported_algorithms()
+{
+    let signatures = XmlSecSignatureAlgorithm::supported_algorithms();
+
+    assert!(signatures.contains(&XmlSecSignatureAlgorithm::RsaSha256));
+    assert!(signatures.contains(&XmlSecSignatureAlgorithm::HmacSha256));
+
+    for method in signatures {
+        assert!(method.is_supported(), "{:?} listed though not supported", method);
+    }
+
+    assert!(XmlSecDigestAlgorithm::supported_algorithms().contains(&XmlSecDigestAlgorithm::Sha256));
+    assert!(XmlSecBlockCipher::supported_algorithms().contains(&XmlSecBlockCipher::Aes128Cbc));
+    assert!(XmlSecKeyTransport::supported_algorithms().contains(&XmlSecKeyTransport::KWAes128));
+}
+
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_method_conversion()
+{
+    use xmlsec::XmlSecSignatureMethod;
+
+    assert_eq!(XmlSecSignatureMethod::from(XmlSecDigestAlgorithm::Sha256), XmlSecSignatureMethod::Sha256);
+    assert_eq!(
+        XmlSecSignatureAlgorithm::try_from(XmlSecSignatureMethod::RsaSha256).ok(),
+        Some(XmlSecSignatureAlgorithm::RsaSha256)
+    );
+    assert_eq!(XmlSecBlockCipher::try_from(XmlSecSignatureMethod::Des3Cbc).ok(), Some(XmlSecBlockCipher::Des3Cbc));
+    assert_eq!(XmlSecKeyTransport::try_from(XmlSecSignatureMethod::KWAes128).ok(), Some(XmlSecKeyTransport::KWAes128));
+
+    assert_eq!(
+        XmlSecDigestAlgorithm::Sha512.to_method(),
+        XmlSecSignatureMethod::Sha512.to_method(),
+        "Both should resolve to the same transform"
+    );
+
+    assert_eq!(
+        XmlSecSignatureMethod::from_method(XmlSecSignatureAlgorithm::HmacSha256.to_method()),
+        Some(XmlSecSignatureMethod::HmacSha256)
+    );
+}
+
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_method_conversion_misuse()
+{
+    use xmlsec::XmlSecSignatureMethod;
+
+    let err = XmlSecSignatureAlgorithm::try_from(XmlSecSignatureMethod::Sha1)
+        .expect_err("A digest should not convert into a signature algorithm");
+
+    assert_eq!(err.to_string(), "Sha1 is not a signature algorithm");
+
+    let doc = XmlParser::default()
+        .parse_string("<root/>")
+        .expect("Failed to parse document");
+
+    let result = doc.template()
+        .signature(XmlSecSignatureMethod::Sha1)
+        .done();
+
+    assert!(result.is_err(), "Building a template with a digest as signature method should fail");
+
+    let result = doc.template()
+        .reference(XmlSecReferenceBuilder::new().digest(XmlSecSignatureMethod::RsaSha256))
+        .done();
+
+    assert!(result.is_err(), "Building a template with a signature method as digest should fail");
+}
+
+
+#[test]
+fn test_versions()
+{
+    let versions = XmlSec::versions();
+
+    assert!(versions.xmlsec.starts_with("1."), "Unexpected xmlsec version {}", versions.xmlsec);
+    assert!(versions.xmlsec_compatible, "Runtime xmlsec should match the one built against");
+    assert!(versions.libxml2.starts_with("2."), "Unexpected libxml2 version {}", versions.libxml2);
+
+    assert!(versions.crypto.is_some(), "Crypto library should report its version");
+
+    XmlSec::check_version()
+        .expect("Runtime xmlsec should be compatible");
+}
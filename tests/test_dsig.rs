@@ -5,8 +5,35 @@ use xmlsec::XmlSecKey;
 use xmlsec::XmlSecKeyFormat;
 use xmlsec::XmlSecSignatureContext;
 use xmlsec::XmlSecDocumentExt;
-
-use libxml::parser::Parser as XmlParser;
+use xmlsec::XmlSecSignatureAlgorithm;
+use xmlsec::XmlSecDigestAlgorithm;
+#[cfg(not(feature = "nss"))]
+use xmlsec::XmlSecVerificationStatus;
+#[cfg(not(feature = "nss"))]
+use xmlsec::XmlSecReferenceOrigin;
+#[cfg(not(feature = "nss"))]
+use xmlsec::XmlSecReferenceUris;
+use xmlsec::XmlSecCanonicalizationMethod;
+use xmlsec::XmlSecError;
+use xmlsec::XmlSecTemplateBuilder;
+use xmlsec::XmlSecDocumentTemplating;
+use xmlsec::XmlSecReferenceBuilder;
+#[cfg(not(feature = "nss"))]
+use xmlsec::XmlSecObjectBuilder;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecKeyInfoBuilder;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecKeysManager;
+use xmlsec::XmlSecSignaturePlacement;
+#[cfg(not(feature = "nss"))]
+use xmlsec::XmlSecTransform;
+#[cfg(not(feature = "nss"))]
+use xmlsec::XmlSecXPath2Filter;
+
+use libxml::parser::Parser           as XmlParser;
+use libxml::tree::document::Document as XmlDocument;
+use libxml::tree::node::Node         as XmlNode;
+use libxml::xpath::Context          as XmlXPathContext;
 
 
 #[test]
@@ -21,8 +48,7 @@ fn test_dsig_key_setting()
 {
     let mut ctx = XmlSecSignatureContext::new();
 
-    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
-        .expect("Failed to properly load key for test");
+    let key = common_load_key("key");
 
     let key_ptr = unsafe { key.as_ptr() };
 
@@ -62,6 +88,7 @@ fn test_signing_template()
 
 
 #[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
 fn test_verify_template_signature()
 {
     let ctx = common_setup_context_and_key();
@@ -86,6 +113,7 @@ fn test_verify_template_signature()
 
 
 #[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
 fn test_verify_custom_id_signature()
 {
     let ctx = common_setup_context_and_key();
@@ -112,14 +140,840 @@ fn test_verify_custom_id_signature()
 }
 
 
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_verification_report()
+{
+    let ctx = common_setup_context_and_key();
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signature for verification testing");
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid);
+
+    let report = ctx.verification_report();
+
+    assert_eq!(report.status, XmlSecVerificationStatus::Succeeded);
+    assert_eq!(report.signature_method, Some(XmlSecSignatureAlgorithm::RsaSha1));
+    assert_eq!(report.signature_method_uri.as_deref(), Some("http://www.w3.org/2000/09/xmldsig#rsa-sha1"));
+    assert_eq!(report.canonicalization_uri.as_deref(), Some("http://www.w3.org/TR/2001/REC-xml-c14n-20010315"));
+    assert!(report.certificate_subject.is_none());
+    assert!(! report.certificate_verified);
+
+    assert_eq!(report.references.len(), 1);
+
+    let reference = &report.references[0];
+
+    assert_eq!(reference.origin, XmlSecReferenceOrigin::SignedInfo);
+    assert_eq!(reference.status, XmlSecVerificationStatus::Succeeded);
+    assert_eq!(reference.uri.as_deref(), Some(""));
+    assert_eq!(reference.digest_method, Some(XmlSecDigestAlgorithm::Sha1));
+    assert_eq!(reference.digest_method_uri.as_deref(), Some("http://www.w3.org/2000/09/xmldsig#sha1"));
+
+    // whole document is covered
+    let signed = ctx.signed_nodes(&doc);
+
+    assert_eq!(signed.len(), 1);
+    assert_eq!(signed[0].get_name(), "Envelope");
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_verification_report_tampered()
+{
+    let ctx = common_setup_context_and_key();
+
+    let signed = String::from_utf8(
+        include_bytes!("./resources/sign1-res.xml").to_vec()
+    ).unwrap();
+
+    let doc = XmlParser::default()
+        .parse_string(signed.replace("Hello, World!", "Hello, Mallory!"))
+        .expect("Failed to load tampered signature for verification testing");
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(! valid, "Tampered document should not have been verified");
+
+    let report = ctx.verification_report();
+
+    assert_eq!(report.status, XmlSecVerificationStatus::Invalid);
+    assert_eq!(report.references.len(), 1);
+    assert_eq!(report.references[0].status, XmlSecVerificationStatus::Invalid);
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_signed_nodes()
+{
+    let mut ctx = common_setup_context_and_key();
+    ctx.set_store_references(true);
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign3-signed.xml")
+        .expect("Failed to load signature for verification testing");
+
+    doc.specify_idattr("//sig:Data", "ThisID", Some(&[("sig", "urn:envelope")]))
+        .expect("Unable to set 'ThisID' as the ID attribute name");
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid);
+
+    let signed = ctx.signed_nodes(&doc);
+
+    assert_eq!(signed.len(), 1);
+    assert_eq!(signed[0].get_attribute("ThisID").as_deref(), Some("1"));
+
+    // only the first <Data/> is covered, the second one merely sits next to it
+    let dataset = doc.get_root_element()
+        .and_then(|root| root.get_first_element_child())
+        .expect("Document should have a <DataSet/> node");
+
+    let data = dataset.get_child_elements();
+
+    assert!(ctx.is_signed(&data[0]));
+    assert!(! ctx.is_signed(&data[1]), "Sibling of the signed node should not be covered");
+    assert!(! ctx.is_signed(&dataset), "Parent of the signed node should not be covered");
+
+    assert!(ctx.ensure_signed(&data[0]).is_ok());
+    assert!(ctx.ensure_signed(&data[1]).is_err());
+
+    // digested data is kept around
+    let report = ctx.verification_report();
+
+    let digested = report.references[0].pre_digest.as_ref()
+        .expect("Should have kept the digested data");
+
+    let digested = String::from_utf8_lossy(digested);
+
+    assert!(digested.contains("Hello"));
+    assert!(! digested.contains("Goodbye"));
+
+    // nodes of another document are never covered
+    let other = XmlParser::default()
+        .parse_file("tests/resources/sign3-signed.xml")
+        .expect("Failed to load document");
+
+    assert!(ctx.signed_nodes(&other).is_empty());
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_signed_nodes_invalid_signature()
+{
+    let ctx = common_setup_context_and_key();
+
+    let signed = String::from_utf8(
+        include_bytes!("./resources/sign1-res.xml").to_vec()
+    ).unwrap();
+
+    let doc = XmlParser::default()
+        .parse_string(signed.replace("Hello, World!", "Hello, Mallory!"))
+        .expect("Failed to load tampered signature for verification testing");
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(! valid);
+    assert!(ctx.signed_nodes(&doc).is_empty(), "Nothing should be covered by an invalid signature");
+
+    let root = doc.get_root_element()
+        .expect("Document should have a root");
+
+    assert!(! ctx.is_signed(&root));
+    assert!(ctx.verification_report().references[0].pre_digest.is_none(), "Digested data should not be kept by default");
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_policy_allowed_algorithms()
+{
+    let mut ctx = common_setup_context_and_key();
+
+    ctx.allow_signature_method(XmlSecSignatureAlgorithm::RsaSha1).expect("Failed to allow signature method");
+    ctx.allow_digest_method(XmlSecDigestAlgorithm::Sha1).expect("Failed to allow digest method");
+    ctx.allow_canonicalization(XmlSecCanonicalizationMethod::InclusiveC14N).expect("Failed to allow c14n");
+    ctx.allow_enveloped_transform().expect("Failed to allow enveloped transform");
+    ctx.set_reference_uris(XmlSecReferenceUris::SameDocument);
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signature for verification testing");
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document within policy");
+
+    assert!(valid);
+}
+
+
+#[test]
+fn test_policy_disallowed_signature_method()
+{
+    let mut ctx = common_setup_context_and_key();
+
+    ctx.allow_signature_method(XmlSecSignatureAlgorithm::RsaSha256).expect("Failed to allow signature method");
+    ctx.allow_canonicalization(XmlSecCanonicalizationMethod::InclusiveC14N).expect("Failed to allow c14n");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signature for verification testing");
+
+    match ctx.verify_document(&doc)
+    {
+        Err(XmlSecError::NotAllowed(what)) => assert_eq!(what, "http://www.w3.org/2000/09/xmldsig#rsa-sha1"),
+        other => panic!("Should have rejected SHA-1 signature, got: {:?}", other),
+    }
+}
+
+
+#[test]
+fn test_policy_signature_method_restricts_c14n()
+{
+    let mut ctx = common_setup_context_and_key();
+
+    // allowing the signature method alone leaves no canonicalization method for <ds:SignedInfo/>
+    ctx.allow_signature_method(XmlSecSignatureAlgorithm::RsaSha1).expect("Failed to allow signature method");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signature for verification testing");
+
+    match ctx.verify_document(&doc)
+    {
+        Err(XmlSecError::NotAllowed(what)) => assert_eq!(what, "http://www.w3.org/TR/2001/REC-xml-c14n-20010315"),
+        other => panic!("Should have rejected the canonicalization method, got: {:?}", other),
+    }
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_policy_disallowed_digest_method()
+{
+    let mut ctx = common_setup_context_and_key();
+
+    ctx.allow_digest_method(XmlSecDigestAlgorithm::Sha256).expect("Failed to allow digest method");
+    ctx.allow_enveloped_transform().expect("Failed to allow enveloped transform");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signature for verification testing");
+
+    match ctx.verify_document(&doc)
+    {
+        Err(XmlSecError::NotAllowed(what)) => assert_eq!(what, "http://www.w3.org/2000/09/xmldsig#sha1"),
+        other => panic!("Should have rejected SHA-1 digest, got: {:?}", other),
+    }
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_policy_disallowed_uri()
+{
+    let mut ctx = common_setup_context_and_key();
+    ctx.set_reference_uris(XmlSecReferenceUris::SameDocument);
+
+    let signed = String::from_utf8(
+        include_bytes!("./resources/sign1-res.xml").to_vec()
+    ).unwrap();
+
+    let doc = XmlParser::default()
+        .parse_string(signed.replace("URI=\"\"", "URI=\"http://example.com/data.xml\""))
+        .expect("Failed to load signature for verification testing");
+
+    match ctx.verify_document(&doc)
+    {
+        Err(XmlSecError::NotAllowed(what)) => assert_eq!(what, "http://example.com/data.xml"),
+        other => panic!("Should have rejected remote reference, got: {:?}", other),
+    }
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_sign_verify_multiple_references()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign4-doc.xml")
+        .expect("Failed to load document to sign");
+
+    doc.specify_idattr("//env:Body|//env:Timestamp", "ID", Some(&[("env", "urn:envelope")]))
+        .expect("Unable to set 'ID' as the ID attribute name");
+
+    doc.template()
+        .signature(XmlSecSignatureAlgorithm::RsaSha256)
+        .reference(XmlSecReferenceBuilder::new().uri("#body").digest(XmlSecDigestAlgorithm::Sha256))
+        .reference(XmlSecReferenceBuilder::new().uri("#ts").digest(XmlSecDigestAlgorithm::Sha256))
+        .done()
+        .expect("Failed to build signature template");
+
+    let ctx = common_setup_context_and_key();
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let ctx = common_setup_context_and_key();
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid);
+
+    let report = ctx.verification_report();
+
+    let uris: Vec<_> = report.references.iter()
+        .map(|r| (r.uri.as_deref().unwrap_or_default().to_owned(), r.status))
+        .collect();
+
+    assert_eq!(uris, vec![
+        ("#body".to_owned(), XmlSecVerificationStatus::Succeeded),
+        ("#ts".to_owned(),   XmlSecVerificationStatus::Succeeded),
+    ]);
+
+    assert_eq!(ctx.signed_nodes(&doc).len(), 2);
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_sign_verify_xpath2_filtered_reference()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign4-doc.xml")
+        .expect("Failed to load document to sign");
+
+    // only sign the body, leaving the header free to change
+    doc.template()
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("")
+            .transform(XmlSecTransform::XPath2 {
+                filter:     XmlSecXPath2Filter::Intersect,
+                expression: "//env:Body".to_owned(),
+                namespaces: vec![("env".to_owned(), "urn:envelope".to_owned())],
+            })
+            .transform(XmlSecTransform::ExclusiveC14N {with_comments: false, prefix_list: vec!["#default".to_owned()]}))
+        .done()
+        .expect("Failed to build signature template");
+
+    let ctx = common_setup_context_and_key();
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let signed = doc.to_string();
+
+    let verify = |xml: String| {
+        let doc = XmlParser::default()
+            .parse_string(xml)
+            .expect("Failed to parse signed document");
+
+        let mut ctx = common_setup_context_and_key();
+
+        ctx.allow_transform(&XmlSecTransform::XPath2 {
+            filter:     XmlSecXPath2Filter::Intersect,
+            expression: String::new(),
+            namespaces: Vec::new(),
+        }).expect("Failed to allow transform");
+
+        ctx.allow_transform(&XmlSecTransform::ExclusiveC14N {with_comments: false, prefix_list: Vec::new()})
+            .expect("Failed to allow transform");
+
+        ctx.allow_digest_method(XmlSecDigestAlgorithm::Sha1)
+            .expect("Failed to allow digest method");
+
+        ctx.verify_document(&doc)
+            .expect("Failed to verify document")
+    };
+
+    assert!(verify(signed.clone()));
+    assert!(verify(signed.replace("2020-01-01", "2030-01-01")), "Header is outside the signed subset");
+    assert!(! verify(signed.replace("Hello, World!", "Hello, Mallory!")), "Body is within the signed subset");
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_sign_verify_detached()
+{
+    let attachment = b"%PDF-1.4 not really a pdf".to_vec();
+
+    // signature is the sole content of its document, pointing at data kept elsewhere
+    let doc = XmlDocument::new()
+        .expect("Failed to create empty document");
+
+    doc.template()
+        .signature(XmlSecSignatureAlgorithm::RsaSha256)
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("attachment.pdf")
+            .digest(XmlSecDigestAlgorithm::Sha256))
+        .done()
+        .expect("Failed to build detached signature template");
+
+    let mut ctx = common_setup_context_and_key();
+
+    let data = attachment.clone();
+    ctx.set_uri_resolver(move |uri| (uri == "attachment.pdf").then(|| data.clone()));
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign detached data");
+
+    let signed = doc.to_string();
+
+    let verify = |data: Vec<u8>| {
+        let doc = XmlParser::default()
+            .parse_string(&signed)
+            .expect("Failed to parse detached signature");
+
+        let mut ctx = common_setup_context_and_key();
+        ctx.set_uri_resolver(move |uri| (uri == "attachment.pdf").then(|| data.clone()));
+
+        ctx.verify_document(&doc)
+    };
+
+    assert!(verify(attachment.clone()).expect("Failed to verify detached signature"));
+    assert!(! verify(b"tampered".to_vec()).expect("Failed to verify detached signature"));
+
+    // without a resolver answering for it the data is never fetched
+    let doc = XmlParser::default()
+        .parse_string(&signed)
+        .expect("Failed to parse detached signature");
+
+    let mut ctx = common_setup_context_and_key();
+    ctx.set_uri_resolver(|_| None);
+
+    assert!(ctx.verify_document(&doc).is_err(), "Should have failed to resolve the attachment");
+
+    // nor does a panicking resolver unwind through xmlsec
+    let mut ctx = common_setup_context_and_key();
+    ctx.set_uri_resolver(|uri| panic!("Resolver failed on {}", uri));
+
+    assert!(ctx.verify_document(&doc).is_err(), "A panicking resolver should count as not resolving");
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_sign_verify_enveloping()
+{
+    let content = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load content to envelop");
+
+    let payload = content.get_root_element()
+        .expect("Content should have a root");
+
+    let doc = XmlDocument::new()
+        .expect("Failed to create empty document");
+
+    doc.template()
+        .signature(XmlSecSignatureAlgorithm::RsaSha256)
+        .reference_signature(XmlSecDigestAlgorithm::Sha256)
+        .object(XmlSecObjectBuilder::node("payload", &payload).mime_type("text/xml"))
+        .object(XmlSecObjectBuilder::data("blob", b"\x00\x01 raw bytes \xff").mime_type("application/octet-stream"))
+        .done()
+        .expect("Failed to build enveloping signature template");
+
+    let root = doc.get_root_element()
+        .expect("Signature should have become the document root");
+
+    assert_eq!(root.get_name(), "Signature");
+
+    // content got copied over from its own document
+    assert!(content.get_root_element().is_some());
+
+    let ctx = common_setup_context_and_key();
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign enveloping signature");
+
+    let signed = doc.to_string();
+
+    assert!(signed.contains("<Object Id=\"blob\" MimeType=\"application/octet-stream\" Encoding=\"http://www.w3.org/2000/09/xmldsig#base64\">AAEgcmF3IGJ5dGVzIP8=</Object>"));
+
+    let verify = |xml: String| {
+        let doc = XmlParser::default()
+            .parse_string(xml)
+            .expect("Failed to parse signed document");
+
+        let ctx = common_setup_context_and_key();
+
+        let valid = ctx.verify_document(&doc)
+            .expect("Failed to verify document");
+
+        (valid, ctx.verification_report().references.len())
+    };
+
+    assert_eq!(verify(signed.clone()), (true, 2));
+    assert!(! verify(signed.replace("AAEgcmF3IGJ5dGVzIP8=", "AAEgcmF3IGJ5dGVzIP4=")).0);
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // the leaf key and CA are PEM, which only the OpenSSL backend reads
+fn test_sign_keyinfo_x509_content()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to sign");
+
+    doc.template()
+        .uri("")
+        .keyinfo(XmlSecKeyInfoBuilder::new()
+            .keyname(Some("partner-key"))
+            .x509_subject_name()
+            .x509_data()
+            .x509_issuer_serial()
+            .x509_data()
+            .x509_ski()
+            .x509_data()
+            .x509_certificate())
+        .done()
+        .expect("Failed to build signature template");
+
+    let key = XmlSecKey::from_file("tests/resources/leaf.key", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    key.load_cert_from_file("tests/resources/leaf.crt", XmlSecKeyFormat::CertPem)
+        .expect("Failed to load certificate for key");
+
+    let mut ctx = XmlSecSignatureContext::new();
+    ctx.insert_key(key);
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let signed = doc.to_string();
+
+    assert!(signed.contains("<KeyName>partner-key</KeyName>"));
+    assert!(signed.contains("<X509IssuerName>"));
+    assert!(signed.contains("<X509SerialNumber>"));
+    assert!(signed.contains("<X509SubjectName>"));
+    assert!(signed.contains("<X509SKI>"));
+    assert!(signed.contains("<X509Certificate>"));
+
+    // partner resolves the key from the certificate carried along
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    mngr.load_cert_from_file("tests/resources/ca.crt", XmlSecKeyFormat::CertPem, true)
+        .expect("Failed to load trusted certificate");
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    assert!(ctx.verify_document(&doc).expect("Failed to verify document"));
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_sign_verify_nested_nodes()
+{
+    let signed = common_sign_saml_response();
+
+    let verify = |xml: &str| {
+        let doc = common_parse_saml(xml);
+
+        let root      = doc.get_root_element().unwrap();
+        let assertion = common_find_saml(&doc, "//saml:Assertion");
+        let signature = common_find_saml(&doc, "//saml:Assertion/dsig:Signature");
+
+        let ctx = common_setup_context_and_key();
+
+        let by_assertion = ctx.verify_node(&assertion).expect("Failed to verify assertion");
+        let by_signature = ctx.verify_node(&signature).expect("Failed to verify assertion signature");
+        let by_response  = ctx.verify_node(&root).expect("Failed to verify response");
+
+        assert_eq!(by_assertion, by_signature);
+        assert_eq!(by_response, ctx.verify_document(&doc).expect("Failed to verify document"));
+
+        (by_assertion, by_response)
+    };
+
+    assert_eq!(verify(&signed), (true, true));
+
+    // the response covers the assertion, but not the other way around
+    assert_eq!(verify(&signed.replace("john.doe@example.com", "mallory@example.com")), (false, false));
+    assert_eq!(verify(&signed.replace("status:Success", "status:Requester")), (true, false));
+}
+
+
+#[test]
+fn test_verify_node_without_signature()
+{
+    let doc = common_parse_saml(&common_sign_saml_response());
+
+    let status = common_find_saml(&doc, "//samlp:Status");
+
+    let ctx = common_setup_context_and_key();
+
+    match ctx.verify_node(&status)
+    {
+        Err(XmlSecError::NodeNotFound) => (),
+        other => panic!("Should not have found a signature under status, got: {:?}", other),
+    }
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_sign_verify_all()
+{
+    let doc = common_parse_dte(&std::fs::read_to_string("tests/resources/sign7-doc.xml").unwrap());
+
+    let root = doc.get_root_element().unwrap();
+
+    // a signature per document, placed beside it, plus one over the whole set
+    for documento in root.findnodes("//*[local-name()='Documento']").unwrap()
+    {
+        let id = documento.get_attribute("ID").unwrap();
+
+        doc.template()
+            .signature(XmlSecSignatureAlgorithm::RsaSha1)
+            .reference(XmlSecReferenceBuilder::new().uri(&format!("#{}", id)))
+            .placement(XmlSecSignaturePlacement::After(documento))
+            .done()
+            .expect("Failed to build document signature template");
+    }
+
+    doc.template()
+        .signature(XmlSecSignatureAlgorithm::RsaSha1)
+        .reference(XmlSecReferenceBuilder::new().uri("#SetDoc"))
+        .done()
+        .expect("Failed to build envelope signature template");
+
+    let ctx = common_setup_context_and_key();
+
+    ctx.sign_all(&doc)
+        .expect("Failed to sign all signatures");
+
+    let signed = doc.to_string();
+
+    let verify = |xml: &str| -> Vec<(String, bool)> {
+        let doc = common_parse_dte(xml);
+
+        let ctx = common_setup_context_and_key();
+
+        ctx.verify_all(&doc)
+            .expect("Failed to verify all signatures")
+            .into_iter()
+            .map(|r| {
+                assert!(r.error.is_none());
+                assert_eq!(r.report.references.len(), 1);
+
+                (r.xpath, r.valid)
+            })
+            .collect()
+    };
+
+    assert_eq!(verify(&signed), vec![
+        ("/*/*[1]/*[1]/*[2]".to_owned(), true),
+        ("/*/*[1]/*[2]/*[2]".to_owned(), true),
+        ("/*/*[2]".to_owned(),           true),
+    ]);
+
+    // tampering with a document breaks its own signature and the envelope, but not the other document
+    let valid: Vec<_> = verify(&signed.replace("<Folio>1</Folio>", "<Folio>3</Folio>"))
+        .into_iter()
+        .map(|(_, valid)| valid)
+        .collect();
+
+    assert_eq!(valid, vec![false, true, false]);
+
+    // checking the document as a whole takes its first signature in document order, that of the first document
+    let tampered = common_parse_dte(&signed.replace("<Folio>2</Folio>", "<Folio>4</Folio>"));
+
+    let valid = common_setup_context_and_key().verify_document(&tampered)
+        .expect("Failed to verify document");
+
+    assert!(valid, "Should have verified the signature of the first document, not the one of the envelope");
+}
+
+
+#[cfg(not(feature = "nss"))]
+fn common_parse_dte(xml: &str) -> XmlDocument
+{
+    let doc = XmlParser::default()
+        .parse_string(xml)
+        .expect("Failed to parse DTE envelope");
+
+    doc.specify_idattr("//sii:SetDTE|//sii:Documento", "ID", Some(&[("sii", "http://www.sii.cl/SiiDte")]))
+        .expect("Unable to set 'ID' as the ID attribute name");
+
+    doc
+}
+
+
+/// Signs the assertion within the SAML response, then the response itself, as an identity provider would.
+fn common_sign_saml_response() -> String
+{
+    let doc = common_parse_saml(&std::fs::read_to_string("tests/resources/sign6-doc.xml").unwrap());
+
+    let root      = doc.get_root_element().unwrap();
+    let assertion = common_find_saml(&doc, "//saml:Assertion");
+
+    doc.template()
+        .canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .signature(XmlSecSignatureAlgorithm::RsaSha256)
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("#assertion")
+            .digest(XmlSecDigestAlgorithm::Sha256)
+            .enveloped(true))
+        .placement(XmlSecSignaturePlacement::After(common_find_saml(&doc, "//saml:Assertion/saml:Issuer")))
+        .done()
+        .expect("Failed to build assertion signature template");
+
+    doc.template()
+        .canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .signature(XmlSecSignatureAlgorithm::RsaSha256)
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("#response")
+            .digest(XmlSecDigestAlgorithm::Sha256)
+            .enveloped(true))
+        .placement(XmlSecSignaturePlacement::After(common_find_saml(&doc, "/samlp:Response/saml:Issuer")))
+        .done()
+        .expect("Failed to build response signature template");
+
+    let ctx = common_setup_context_and_key();
+
+    ctx.sign_node(&assertion)
+        .expect("Failed to sign assertion");
+
+    ctx.sign_node(&root)
+        .expect("Failed to sign response");
+
+    doc.to_string()
+}
+
+
+fn common_parse_saml(xml: &str) -> XmlDocument
+{
+    let doc = XmlParser::default()
+        .parse_string(xml)
+        .expect("Failed to parse SAML response");
+
+    doc.specify_idattr("//samlp:Response|//saml:Assertion", "ID", Some(&[
+        ("samlp", "urn:oasis:names:tc:SAML:2.0:protocol"),
+        ("saml",  "urn:oasis:names:tc:SAML:2.0:assertion"),
+    ])).expect("Unable to set 'ID' as the ID attribute name");
+
+    doc
+}
+
+
+fn common_find_saml(doc: &XmlDocument, search: &str) -> XmlNode
+{
+    let mut xpath = XmlXPathContext::new(doc)
+        .expect("Could not build xpath context");
+
+    xpath.register_namespace("samlp", "urn:oasis:names:tc:SAML:2.0:protocol").unwrap();
+    xpath.register_namespace("saml",  "urn:oasis:names:tc:SAML:2.0:assertion").unwrap();
+    xpath.register_namespace("dsig",  "http://www.w3.org/2000/09/xmldsig#").unwrap();
+
+    xpath.findnodes(search, None)
+        .expect("Could not search nodes")
+        .into_iter()
+        .next()
+        .expect("Could not find node")
+}
+
+
+#[test]
+fn test_sign_verify_hmac()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to sign");
+
+    doc.template()
+        .signature(XmlSecSignatureAlgorithm::HmacSha256)
+        .hmac_output_length(128)
+        .uri("")
+        .done()
+        .expect("Failed to build signature template");
+
+    let mut ctx = XmlSecSignatureContext::new();
+    ctx.insert_key(XmlSecKey::from_hmac_secret(b"shared secret").expect("Failed to build HMAC key"));
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let signed = doc.to_string();
+
+    assert!(signed.contains("<HMACOutputLength>128</HMACOutputLength>"));
+
+    let verify = |secret: &[u8]| {
+        let doc = XmlParser::default()
+            .parse_string(&signed)
+            .expect("Failed to parse signed document");
+
+        let mut ctx = XmlSecSignatureContext::new();
+        ctx.insert_key(XmlSecKey::from_hmac_secret(secret).expect("Failed to build HMAC key"));
+
+        ctx.verify_document(&doc)
+            .expect("Failed to verify document")
+    };
+
+    assert!(verify(b"shared secret"));
+    assert!(! verify(b"another secret"));
+}
+
+
+#[test]
+fn test_hmac_output_length_requires_hmac()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to sign");
+
+    let result = doc.template()
+        .signature(XmlSecSignatureAlgorithm::RsaSha256)
+        .hmac_output_length(128)
+        .uri("")
+        .done();
+
+    assert!(result.is_err(), "HMAC output length should be refused for RSA signatures");
+    assert!(! doc.to_string().contains("HMACOutputLength"), "Nothing should have been added to the document");
+}
+
+
 fn common_setup_context_and_key() -> XmlSecSignatureContext
 {
    let mut ctx = XmlSecSignatureContext::new();
 
-   let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
-       .expect("Failed to properly load key for test");
+   let key = common_load_key("key");
 
     ctx.insert_key(key);
 
     ctx
 }
+
+
+/// Loads one of the RSA test keys, in a format the crypto backend reads: PEM is only supported by OpenSSL, while NSS
+/// takes PKCS#8 and GnuTLS and GCrypt take PKCS#1 DER.
+fn common_load_key(name: &str) -> XmlSecKey
+{
+    #[cfg(not(any(feature = "nss", feature = "gnutls", feature = "gcrypt")))]
+    let (path, format) = (format!("tests/resources/{}.pem", name), XmlSecKeyFormat::Pem);
+
+    #[cfg(feature = "nss")]
+    let (path, format) = (format!("tests/resources/{}.pkcs8.der", name), XmlSecKeyFormat::Der);
+
+    #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+    let (path, format) = (format!("tests/resources/{}.der", name), XmlSecKeyFormat::Der);
+
+    XmlSecKey::from_file(&path, format, None)
+        .expect("Failed to properly load key for test")
+}
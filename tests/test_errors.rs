@@ -0,0 +1,74 @@
+//!
+//! Unit Tests for Library Error Reporting
+//!
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecError;
+use xmlsec::XmlSecKeysManager;
+use xmlsec::XmlSecSignatureContext;
+
+use libxml::parser::Parser as XmlParser;
+
+
+#[test]
+fn test_key_load_error_details()
+{
+    let err = XmlSecKey::from_memory(b"not a key", XmlSecKeyFormat::Pem, None)
+        .expect_err("Should not have loaded garbage as a key");
+
+    match &err
+    {
+        XmlSecError::Library {kind, details} => {
+            assert!(matches!(**kind, XmlSecError::KeyLoadError), "Should have kept the kind of failure");
+            assert!(! details.is_empty(), "Should have collected the errors raised by xmlsec");
+        },
+
+        other => panic!("Should have been a library error, got: {:?}", other),
+    }
+
+    assert!(err.to_string().starts_with("Failed to load key: "));
+}
+
+
+#[test]
+fn test_verify_error_details()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signed document");
+
+    // nothing to resolve the key from
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    let err = ctx.verify_document(&doc)
+        .expect_err("Should have failed to find a key");
+
+    assert!(
+        err.details().iter().any(|r| r.reason == 45 && r.reason_msg == "key is not found"),
+        "Should have reported the missing key, got: {:?}", err
+    );
+
+    assert!(matches!(err.kind(), XmlSecError::VerifyError), "Should still be a verification failure");
+    assert!(err.to_string().starts_with("Verification process failed: "));
+}
+
+
+#[test]
+fn test_errors_do_not_leak_between_operations()
+{
+    XmlSecKey::from_memory(b"not a key", XmlSecKeyFormat::Pem, None)
+        .expect_err("Should not have loaded garbage as a key");
+
+    let err = XmlSecKey::from_memory(b"", XmlSecKeyFormat::Binary, None)
+        .expect_err("Should not have loaded an empty key");
+
+    let count = err.details().len();
+
+    let again = XmlSecKey::from_memory(b"", XmlSecKeyFormat::Binary, None)
+        .expect_err("Should not have loaded an empty key");
+
+    assert_eq!(again.details().len(), count, "Records of previous operations should not be carried over");
+}
@@ -0,0 +1,188 @@
+//!
+//! Unit Tests for Explicit Initialization
+//!
+use xmlsec::XmlSec;
+use xmlsec::XmlSecConfig;
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecSignatureContext;
+use xmlsec::XmlSecSignatureAlgorithm;
+use xmlsec::XmlSecDigestAlgorithm;
+use xmlsec::XmlSecReferenceBuilder;
+use xmlsec::XmlSecCanonicalizationMethod;
+use xmlsec::XmlSecTemplateBuilder;
+use xmlsec::XmlSecDocumentTemplating;
+
+use libxml::parser::Parser as XmlParser;
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+
+/// xmlsec is initialized once for the whole process, so these tests must not overlap
+static SERIAL: Mutex<()> = Mutex::new(());
+
+
+#[test]
+fn test_init_error_callback()
+{
+    let _serial = common_serialize();
+
+    let count    = Arc::new(AtomicUsize::new(0));
+    let counting = count.clone();
+
+    let _guard = XmlSec::init(XmlSecConfig::new().error_callback(move |_| { counting.fetch_add(1, Ordering::SeqCst); }))
+        .expect("Failed to initialize xmlsec");
+
+    assert_eq!(count.load(Ordering::SeqCst), 0, "Nothing should have failed yet");
+
+    XmlSecKey::from_memory(b"not a key", XmlSecKeyFormat::Pem, None)
+        .expect_err("Garbage should not load as a key");
+
+    assert!(count.load(Ordering::SeqCst) > 0, "Errors should have reached the callback");
+}
+
+
+#[test]
+fn test_init_id_attributes()
+{
+    let _serial = common_serialize();
+
+    let _guard = XmlSec::init(XmlSecConfig::new().id_attribute("ID"))
+        .expect("Failed to initialize xmlsec");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign6-doc.xml")
+        .expect("Failed to load SAML response");
+
+    doc.template()
+        .canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .signature(XmlSecSignatureAlgorithm::RsaSha256)
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("#response")
+            .digest(XmlSecDigestAlgorithm::Sha256)
+            .enveloped(true))
+        .done()
+        .expect("Failed to build signature template");
+
+    let mut ctx = XmlSecSignatureContext::new();
+
+    let key = common_load_key("key");
+
+    ctx.insert_key(key);
+
+    // no call to specify_idattr, the configured ID attribute has to be enough
+    ctx.sign_document(&doc)
+        .expect("Failed to sign by configured ID attribute");
+
+    // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+    #[cfg(not(feature = "nss"))]
+    {
+        let signed = XmlParser::default()
+            .parse_string(doc.to_string())
+            .expect("Failed to parse signed response");
+
+        assert!(ctx.verify_document(&signed).expect("Failed to verify signed response"));
+    }
+}
+
+
+#[test]
+fn test_init_twice()
+{
+    let _serial = common_serialize();
+
+    let guard = XmlSec::init(XmlSecConfig::new())
+        .expect("Failed to initialize xmlsec");
+
+    assert!(XmlSec::init(XmlSecConfig::new()).is_err(), "Second initialization should be refused");
+
+    drop(guard);
+
+    let _guard = XmlSec::init(XmlSecConfig::new())
+        .expect("Should initialize again once shut down");
+}
+
+
+#[test]
+fn test_init_shutdown_deferred_by_live_objects()
+{
+    let _serial = common_serialize();
+
+    let guard = XmlSec::init(XmlSecConfig::new())
+        .expect("Failed to initialize xmlsec");
+
+    let key = XmlSecKey::from_hmac_secret(b"shared secret")
+        .expect("Failed to build HMAC key");
+
+    let mut ctx = XmlSecSignatureContext::new();
+
+    drop(guard);
+
+    assert!(XmlSec::init(XmlSecConfig::new()).is_err(), "Live objects should keep xmlsec from shutting down");
+
+    // still fully usable, the key passing over to the context in between
+    ctx.insert_key(key);
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to sign");
+
+    doc.template()
+        .signature(XmlSecSignatureAlgorithm::HmacSha256)
+        .uri("")
+        .done()
+        .expect("Failed to build signature template");
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign after the guard was dropped");
+
+    assert!(ctx.verify_document(&doc).expect("Failed to verify after the guard was dropped"));
+
+    drop(ctx);
+
+    let _guard = XmlSec::init(XmlSecConfig::new())
+        .expect("Should initialize again once the last object is gone");
+}
+
+
+#[test]
+fn test_init_wrong_backend()
+{
+    let _serial = common_serialize();
+
+    let other = if cfg!(feature = "openssl") { "nss" } else { "openssl" };
+
+    assert!(XmlSec::init(XmlSecConfig::new().crypto_backend(other)).is_err(), "Backend not built against should fail");
+
+    let _guard = XmlSec::init(XmlSecConfig::new())
+        .expect("Failed initialization should leave xmlsec free to initialize");
+}
+
+
+fn common_serialize() -> MutexGuard<'static, ()>
+{
+    SERIAL.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+
+/// Loads one of the RSA test keys, in a format the crypto backend reads: PEM is only supported by OpenSSL, while NSS
+/// takes PKCS#8 and GnuTLS and GCrypt take PKCS#1 DER.
+fn common_load_key(name: &str) -> XmlSecKey
+{
+    #[cfg(not(any(feature = "nss", feature = "gnutls", feature = "gcrypt")))]
+    let (path, format) = (format!("tests/resources/{}.pem", name), XmlSecKeyFormat::Pem);
+
+    #[cfg(feature = "nss")]
+    let (path, format) = (format!("tests/resources/{}.pkcs8.der", name), XmlSecKeyFormat::Der);
+
+    #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+    let (path, format) = (format!("tests/resources/{}.der", name), XmlSecKeyFormat::Der);
+
+    XmlSecKey::from_file(&path, format, None)
+        .expect("Failed to properly load key for test")
+}
@@ -6,6 +6,7 @@ use xmlsec::XmlSecKeyFormat;
 
 
 #[test]
+#[cfg(not(any(feature = "nss", feature = "gnutls", feature = "gcrypt")))]  // PEM is only read by the OpenSSL backend
 fn test_key_cert_loading_file()
 {
     let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
@@ -17,6 +18,7 @@ fn test_key_cert_loading_file()
 
 
 #[test]
+#[cfg(not(any(feature = "nss", feature = "gnutls", feature = "gcrypt")))]  // PEM is only read by the OpenSSL backend
 fn test_key_cert_loading_memory()
 {
     let keybuff = std::fs::read("tests/resources/key.pem")
@@ -33,11 +35,27 @@ fn test_key_cert_loading_memory()
 }
 
 
+#[test]
+#[cfg(not(feature = "gcrypt"))]  // the GCrypt backend has no support for certificates
+fn test_key_cert_loading_der()
+{
+    let key = common_load_key("key");
+
+    key.load_cert_from_file("tests/resources/key.crt.der", common_cert_der_format())
+        .expect("Failed to properly load key certificate for test");
+
+    let crtbuff = std::fs::read("tests/resources/key.crt.der")
+        .expect("Failed to read file for testing cert from memory load");
+
+    key.load_cert_from_memory(&crtbuff, common_cert_der_format())
+        .expect("Failed to properly load key certificate for test");
+}
+
+
 #[test]
 fn test_key_name_handing()
 {
-    let mut key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
-        .expect("Failed to properly load key for test");
+    let mut key = common_load_key("key");
 
     key.set_name("testname");
 
@@ -45,3 +63,29 @@ fn test_key_name_handing()
 
     assert_eq!(name, "testname");
 }
+
+
+/// Loads one of the RSA test keys, in a format the crypto backend reads: PEM is only supported by OpenSSL, while NSS
+/// takes PKCS#8 and GnuTLS and GCrypt take PKCS#1 DER.
+fn common_load_key(name: &str) -> XmlSecKey
+{
+    #[cfg(not(any(feature = "nss", feature = "gnutls", feature = "gcrypt")))]
+    let (path, format) = (format!("tests/resources/{}.pem", name), XmlSecKeyFormat::Pem);
+
+    #[cfg(feature = "nss")]
+    let (path, format) = (format!("tests/resources/{}.pkcs8.der", name), XmlSecKeyFormat::Der);
+
+    #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+    let (path, format) = (format!("tests/resources/{}.der", name), XmlSecKeyFormat::Der);
+
+    XmlSecKey::from_file(&path, format, None)
+        .expect("Failed to properly load key for test")
+}
+
+
+/// Format to load DER certificates with, NSS takes them as plain DER rather than CertDer.
+#[cfg(not(feature = "gcrypt"))]
+fn common_cert_der_format() -> XmlSecKeyFormat
+{
+    if cfg!(feature = "nss") { XmlSecKeyFormat::Der } else { XmlSecKeyFormat::CertDer }
+}
@@ -0,0 +1,512 @@
+//!
+//! Unit Tests for Keys Manager
+//!
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecKeysManager;
+use xmlsec::XmlSecSignatureContext;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecX509Flag;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecEncryptionContext;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecDecryptedData;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecKeyTransport;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecBlockCipher;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecSymmetricKeyKind;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecEncryptionTemplateBuilder;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecDocumentEncryptionTemplating;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecTemplateBuilder;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecDocumentTemplating;
+
+use libxml::parser::Parser           as XmlParser;
+#[cfg(feature = "openssl")]
+use libxml::tree::document::Document as XmlDocument;
+
+use std::time::Duration;
+use std::time::UNIX_EPOCH;
+
+
+#[test]
+fn test_keys_manager_creation()
+{
+    XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+}
+
+
+#[test]
+fn test_keys_manager_find_key()
+{
+    let mngr = common_setup_keys_manager();
+
+    let key = mngr.find_key("testkey")
+        .expect("Failed to look up key")
+        .expect("Should have found key by its name");
+
+    assert_eq!(key.get_name(), "testkey");
+
+    assert!(mngr.find_key("unknownkey").expect("Failed to look up key").is_none(), "Should not have found a key that was never added");
+}
+
+
+#[test]
+fn test_keys_manager_rejects_null_bytes()
+{
+    let mngr = common_setup_keys_manager();
+
+    assert!(mngr.find_key("test\0key").is_err(), "Key name with null byte should be refused");
+    assert!(mngr.load_cert_from_file("tests/resources/ca.crt\0", XmlSecKeyFormat::CertPem, true).is_err());
+}
+
+
+#[test]
+#[cfg(not(feature = "nss"))]  // NSS keeps no public key next to a PKCS#8 private key, so it cannot verify with it
+fn test_sign_verify_with_keys_manager()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-tmpl.xml")
+        .expect("Failed to load signature template");
+
+    // sign with explicit key, which writes its name into <KeyName/>
+    let mut key = common_load_key("key");
+
+    key.set_name("testkey");
+
+    let mut sigctx = XmlSecSignatureContext::new();
+    sigctx.insert_key(key);
+
+    sigctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    assert!(doc.to_string().contains("<KeyName>testkey</KeyName>"));
+
+    // verify resolving the key by name from the manager
+    let mngr = common_setup_keys_manager();
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    drop(mngr);  // context keeps the manager alive
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid, "Signature should have been verified with the key resolved by name");
+}
+
+
+#[test]
+fn test_verify_with_keys_manager_missing_key()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-tmpl.xml")
+        .expect("Failed to load signature template");
+
+    let mut key = common_load_key("key");
+
+    key.set_name("testkey");
+
+    let mut sigctx = XmlSecSignatureContext::new();
+    sigctx.insert_key(key);
+
+    sigctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    // manager only knowing another key
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    let mut other = common_load_key("key2");
+
+    other.set_name("otherkey");
+
+    mngr.add_key(other)
+        .expect("Failed to add key to manager");
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    if let Ok(valid) = ctx.verify_document(&doc) {
+        assert!(!valid, "Signature should not verify with an unrelated key");
+    }
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_decrypt_with_keys_manager()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let data = doc.get_root_element()
+        .and_then(|r| r.get_first_element_child())
+        .expect("Document should have a <Data/> node");
+
+    let encdata = doc.encryption_template()
+        .method(XmlSecKeyTransport::RsaPkcs1)
+        .keyname(true)
+        .done()
+        .expect("Failed to build encryption template");
+
+    let mut key = common_load_key("key");
+
+    key.set_name("testkey");
+
+    let mut encctx = XmlSecEncryptionContext::new();
+    encctx.insert_key(key);
+
+    encctx.encrypt_node(&encdata, &data)
+        .expect("Failed to encrypt");
+
+    assert!(doc.to_string().contains("<KeyName>testkey</KeyName>"));
+
+    let mngr = common_setup_keys_manager();
+
+    let ctx = XmlSecEncryptionContext::with_keys_manager(&mngr);
+
+    match ctx.decrypt_document(&doc)
+    {
+        Ok(XmlSecDecryptedData::Element(node)) => assert_eq!(node.get_name(), "Data"),
+        Ok(other) => panic!("Should have decrypted into an element, got: {:?}", other),
+        Err(e)    => panic!("{}", e),
+    }
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_encrypted_key_round_trip_with_keys_manager()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let data = doc.get_root_element()
+        .and_then(|r| r.get_first_element_child())
+        .expect("Document should have a <Data/> node");
+
+    let encdata = doc.encryption_template()
+        .method(XmlSecBlockCipher::Aes128Cbc)
+        .encrypted_key(XmlSecKeyTransport::RsaPkcs1)
+        .encrypted_keyname(true)
+        .done()
+        .expect("Failed to build encryption template");
+
+    let session = XmlSecKey::from_symmetric(b"0123456789abcdef", XmlSecSymmetricKeyKind::Aes)
+        .expect("Failed to create session key");
+
+    let mngr = common_setup_keys_manager();
+
+    // the session key encrypts the data and is itself wrapped by the RSA key held in the manager
+    let mut encctx = XmlSecEncryptionContext::with_keys_manager(&mngr);
+    encctx.insert_key(session);
+
+    encctx.encrypt_node(&encdata, &data)
+        .expect("Failed to encrypt");
+
+    let encrypted = doc.to_string();
+
+    assert!(encrypted.contains("EncryptedKey"), "Session key should have been wrapped");
+    assert!(!encrypted.contains("Hello, World!"), "Plaintext should be gone");
+
+    let ctx = XmlSecEncryptionContext::with_keys_manager(&mngr);
+
+    match ctx.decrypt_document(&doc)
+    {
+        Ok(XmlSecDecryptedData::Element(node)) => assert_eq!(node.get_name(), "Data"),
+        Ok(other) => panic!("Should have decrypted into an element, got: {:?}", other),
+        Err(e)    => panic!("{}", e),
+    }
+
+    assert!(doc.to_string().contains("Hello, World!"), "Plaintext should be restored");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // certificate handling is only wrapped for the OpenSSL backend
+fn test_verify_with_trusted_certificate()
+{
+    let doc = common_sign_with_certificate("tests/resources/leaf.key", "tests/resources/leaf.crt");
+
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    mngr.load_cert_from_file("tests/resources/ca.crt", XmlSecKeyFormat::CertPem, true)
+        .expect("Failed to load trusted certificate");
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid, "Signature should have been verified with the certificate");
+    assert!(ctx.certificate_verified(), "Certificate should have been validated against the trusted CA");
+
+    let report = ctx.verification_report();
+
+    assert!(report.certificate_verified);
+    assert_eq!(report.certificate_subject.as_deref(), Some("/C=NA/O=rust-xmlsec/CN=leaf"));
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // certificate handling is only wrapped for the OpenSSL backend
+fn test_verify_with_untrusted_certificate()
+{
+    let doc = common_sign_with_certificate("tests/resources/leaf.key", "tests/resources/leaf.crt");
+
+    // the CA is known but not trusted, so the chain has no anchor
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    let ca = std::fs::read("tests/resources/ca.crt")
+        .expect("Failed to read certificate");
+
+    mngr.load_cert_from_memory(&ca, XmlSecKeyFormat::CertPem, false)
+        .expect("Failed to load untrusted certificate");
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    if let Ok(valid) = ctx.verify_document(&doc) {
+        assert!(!valid, "Signature should not verify without a trusted chain");
+    }
+
+    assert!(!ctx.certificate_verified());
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // certificate handling is only wrapped for the OpenSSL backend
+fn test_verify_with_revoked_certificate()
+{
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    mngr.add_trusted_certs_file("tests/resources/ca.crt")
+        .expect("Failed to add trusted certificates file");
+
+    mngr.load_crl_from_file("tests/resources/ca.crl", XmlSecKeyFormat::Pem)
+        .expect("Failed to load revocation list");
+
+    // revoked certificate is rejected
+    let doc = common_sign_with_certificate("tests/resources/revoked.key", "tests/resources/revoked.crt");
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    if let Ok(valid) = ctx.verify_document(&doc) {
+        assert!(!valid, "Signature should not verify with a revoked certificate");
+    }
+
+    assert!(!ctx.certificate_verified());
+
+    // other certificates of the same CA are still fine
+    let doc = common_sign_with_certificate("tests/resources/leaf.key", "tests/resources/leaf.crt");
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid, "Signature should have been verified with a non revoked certificate");
+    assert!(ctx.certificate_verified());
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // certificate handling is only wrapped for the OpenSSL backend
+fn test_load_crl_from_memory()
+{
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    let der = std::fs::read("tests/resources/ca.crl.der")
+        .expect("Failed to read revocation list");
+
+    mngr.load_crl_from_memory(&der, XmlSecKeyFormat::Der)
+        .expect("Failed to load DER revocation list");
+
+    assert!(mngr.load_crl_from_memory(&der, XmlSecKeyFormat::Pem).is_err(), "Should not load DER data as PEM");
+    assert!(mngr.load_crl_from_memory(b"garbage", XmlSecKeyFormat::Der).is_err(), "Should not load garbage");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // certificate handling is only wrapped for the OpenSSL backend
+fn test_certificate_not_verified_for_named_key()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-tmpl.xml")
+        .expect("Failed to load signature template");
+
+    let mut key = common_load_key("key");
+
+    key.set_name("testkey");
+
+    let mut sigctx = XmlSecSignatureContext::new();
+    sigctx.insert_key(key);
+
+    sigctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let mngr = common_setup_keys_manager();
+    let ctx  = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid);
+    assert!(!ctx.certificate_verified(), "Key resolved by name is not backed by a validated certificate");
+}
+
+
+#[cfg(feature = "openssl")]
+fn common_sign_with_certificate(keyfile: &str, certfile: &str) -> XmlDocument
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to sign");
+
+    doc.template()
+        .uri("")
+        .x509data(true)
+        .done()
+        .expect("Failed to build signature template");
+
+    let key = XmlSecKey::from_file(keyfile, XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    key.load_cert_from_file(certfile, XmlSecKeyFormat::CertPem)
+        .expect("Failed to load certificate for key");
+
+    let mut sigctx = XmlSecSignatureContext::new();
+    sigctx.insert_key(key);
+
+    sigctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    assert!(doc.to_string().contains("<X509Certificate>"));
+
+    doc
+}
+
+#[test]
+#[cfg(feature = "openssl")]  // certificate handling is only wrapped for the OpenSSL backend
+fn test_verify_expired_certificate_at_signing_time()
+{
+    let doc = common_sign_with_certificate("tests/resources/key.pem", "tests/resources/key.crt");
+
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    mngr.load_cert_from_file("tests/resources/key.crt", XmlSecKeyFormat::CertPem, true)
+        .expect("Failed to load trusted certificate");
+
+    // certificate expired back in 2020
+    let ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+
+    if let Ok(valid) = ctx.verify_document(&doc) {
+        assert!(!valid, "Signature should not verify with an expired certificate");
+    }
+
+    // but was valid by the end of 2019
+    let mut ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+    ctx.set_certs_verification_time(Some(UNIX_EPOCH + Duration::from_secs(1_577_000_000)))
+        .expect("Failed to set verification time");
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid, "Signature should have been verified as of the given time");
+    assert!(ctx.certificate_verified());
+}
+
+
+#[test]
+fn test_certs_verification_time_before_epoch()
+{
+    let mut ctx = XmlSecSignatureContext::new();
+
+    assert!(ctx.set_certs_verification_time(Some(UNIX_EPOCH - Duration::from_secs(1))).is_err());
+    assert!(ctx.set_certs_verification_time(Some(UNIX_EPOCH)).is_err(), "Epoch itself would mean now to xmlsec");
+
+    ctx.set_certs_verification_time(None)
+        .expect("Should fall back to validating as of now");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // certificate handling is only wrapped for the OpenSSL backend
+fn test_verify_without_certificate_validation()
+{
+    let doc = common_sign_with_certificate("tests/resources/leaf.key", "tests/resources/leaf.crt");
+
+    // nothing trusted at all
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    let mut ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+    ctx.set_certs_verification_depth(4);
+    ctx.set_x509_flag(XmlSecX509Flag::DontVerifyCerts, true);
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(valid, "Signature should have been verified with the unvalidated certificate");
+    assert!(!ctx.certificate_verified(), "Certificate should not count as validated");
+
+    // and back to strict
+    let mut ctx = XmlSecSignatureContext::with_keys_manager(&mngr);
+    ctx.set_x509_flag(XmlSecX509Flag::DontVerifyCerts, true);
+    ctx.set_x509_flag(XmlSecX509Flag::DontVerifyCerts, false);
+
+    if let Ok(valid) = ctx.verify_document(&doc) {
+        assert!(!valid, "Signature should not verify without a trusted chain");
+    }
+}
+
+
+fn common_setup_keys_manager() -> XmlSecKeysManager
+{
+    let mngr = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    let mut other = common_load_key("key2");
+
+    other.set_name("otherkey");
+
+    let mut key = common_load_key("key");
+
+    key.set_name("testkey");
+
+    mngr.add_key(other)
+        .expect("Failed to add key to manager");
+
+    mngr.add_key(key)
+        .expect("Failed to add key to manager");
+
+    mngr
+}
+
+
+/// Loads one of the RSA test keys, in a format the crypto backend reads: PEM is only supported by OpenSSL, while NSS
+/// takes PKCS#8 and GnuTLS and GCrypt take PKCS#1 DER.
+fn common_load_key(name: &str) -> XmlSecKey
+{
+    #[cfg(not(any(feature = "nss", feature = "gnutls", feature = "gcrypt")))]
+    let (path, format) = (format!("tests/resources/{}.pem", name), XmlSecKeyFormat::Pem);
+
+    #[cfg(feature = "nss")]
+    let (path, format) = (format!("tests/resources/{}.pkcs8.der", name), XmlSecKeyFormat::Der);
+
+    #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+    let (path, format) = (format!("tests/resources/{}.der", name), XmlSecKeyFormat::Der);
+
+    XmlSecKey::from_file(&path, format, None)
+        .expect("Failed to properly load key for test")
+}
@@ -3,13 +3,32 @@
 //!
 use xmlsec::XmlSecTemplateBuilder;
 use xmlsec::XmlSecDocumentTemplating;
+use xmlsec::XmlSecReferenceBuilder;
 use xmlsec::XmlSecCanonicalizationMethod;
+use xmlsec::XmlSecTransform;
+use xmlsec::XmlSecXPath2Filter;
+use xmlsec::XmlSecSignaturePlacement;
+use xmlsec::XmlSecKeyInfoBuilder;
+#[allow(deprecated)]
 use xmlsec::XmlSecSignatureMethod;
+use xmlsec::XmlSecBlockCipher;
 
-use libxml::parser::Parser as XmlParser;
+use xmlsec::XmlSecError;
+
+use xmlsec::XmlSecEncryptionTemplateBuilder;
+use xmlsec::XmlSecDocumentEncryptionTemplating;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecKeyTransport;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecEncryptionType;
+
+use libxml::parser::Parser           as XmlParser;
+use libxml::tree::document::Document as XmlDocument;
+use libxml::xpath::Context          as XmlXPathContext;
 
 
 #[test]
+#[allow(deprecated)]
 fn test_template_creation()
 {
     // load document
@@ -38,6 +57,7 @@ fn test_template_creation()
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_template_creation_with_ns_prefix()
 {
     // load document
@@ -65,3 +85,340 @@ fn test_template_creation_with_ns_prefix()
 
     assert_eq!(doc.to_string(), reference);
 }
+
+#[test]
+#[allow(deprecated)]
+fn test_template_creation_with_references()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign4-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .signature(XmlSecSignatureMethod::RsaSha256)
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("#body")
+            .digest(XmlSecSignatureMethod::Sha256)
+            .id("ref-body"))
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("#ts")
+            .digest(XmlSecSignatureMethod::Sha512)
+            .ref_type("urn:envelope:timestamp"))
+        .keyname(true)
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign4-tmpl.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_template_creation_with_transforms()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign4-doc.xml")
+        .expect("Could not load template document");
+
+    let stylesheet = "<xsl:stylesheet xmlns:xsl=\"http://www.w3.org/1999/XSL/Transform\" version=\"1.0\">\
+                      <xsl:output method=\"text\"/></xsl:stylesheet>";
+
+    doc.template()
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("")
+            .transform(XmlSecTransform::Enveloped)
+            .transform(XmlSecTransform::XPath {
+                expression: "not(ancestor-or-self::dsig:Signature)".to_owned(),
+                namespaces: vec![("dsig".to_owned(), "http://www.w3.org/2000/09/xmldsig#".to_owned())],
+            })
+            .transform(XmlSecTransform::XPath2 {
+                filter:     XmlSecXPath2Filter::Subtract,
+                expression: "//env:Header".to_owned(),
+                namespaces: vec![("env".to_owned(), "urn:envelope".to_owned())],
+            })
+            .transform(XmlSecTransform::ExclusiveC14N {
+                with_comments: false,
+                prefix_list:   vec!["env".to_owned(), "#default".to_owned()],
+            }))
+        .reference(XmlSecReferenceBuilder::new()
+            .uri("#body")
+            .transform(XmlSecTransform::Base64)
+            .transform(XmlSecTransform::Xslt(stylesheet.to_owned()))
+            .transform(XmlSecTransform::C14N(XmlSecCanonicalizationMethod::InclusiveC14N)))
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign5-tmpl.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_template_placement()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign6-doc.xml")
+        .expect("Could not load template document");
+
+    let mut xpath = XmlXPathContext::new(&doc)
+        .expect("Could not build xpath context");
+
+    xpath.register_namespace("saml", "urn:oasis:names:tc:SAML:2.0:assertion")
+        .expect("Could not register namespace");
+
+    let issuers = xpath.findnodes("//saml:Issuer", None)
+        .expect("Could not find issuers");
+
+    // SAML wants the signature right after the issuer of the signed element
+    let signature = doc.template()
+        .uri("#assertion")
+        .placement(XmlSecSignaturePlacement::After(issuers[1].clone()))
+        .done()
+        .expect("Failed to build and place signature");
+
+    assert_eq!(signature.get_name(), "Signature");
+    assert_eq!(signature.get_prev_element_sibling(), Some(issuers[1].clone()));
+    assert_eq!(signature.get_parent().unwrap().get_name(), "Assertion");
+
+    // placed before a sibling
+    let signature = doc.template()
+        .uri("#response")
+        .placement(XmlSecSignaturePlacement::Before(issuers[0].clone()))
+        .done()
+        .expect("Failed to build and place signature");
+
+    assert_eq!(signature.get_next_element_sibling(), Some(issuers[0].clone()));
+    assert_eq!(signature.get_parent(), doc.get_root_element());
+
+    // placed under the node found by an xpath
+    let signature = doc.template()
+        .placement(XmlSecSignaturePlacement::XPath {
+            expression: "//saml:Subject".to_owned(),
+            namespaces: vec![("saml".to_owned(), "urn:oasis:names:tc:SAML:2.0:assertion".to_owned())],
+        })
+        .done()
+        .expect("Failed to build and place signature");
+
+    assert_eq!(signature.get_parent().unwrap().get_name(), "Subject");
+    assert!(signature.get_next_sibling().is_none());
+
+    // placed under a given node
+    let status = doc.get_root_element().unwrap()
+        .get_child_elements()
+        .into_iter()
+        .find(|n| n.get_name() == "Status")
+        .unwrap();
+
+    let signature = doc.template()
+        .placement(XmlSecSignaturePlacement::AppendTo(status.clone()))
+        .done()
+        .expect("Failed to build and place signature");
+
+    assert_eq!(signature.get_parent(), Some(status));
+}
+
+#[test]
+fn test_template_placement_errors()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign6-doc.xml")
+        .expect("Could not load template document");
+
+    let other = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load other document");
+
+    let result = doc.template()
+        .placement(XmlSecSignaturePlacement::AppendTo(other.get_root_element().unwrap()))
+        .done();
+
+    assert!(matches!(result, Err(XmlSecError::Str(_))), "Should not place into another document");
+
+    let result = doc.template()
+        .placement(XmlSecSignaturePlacement::After(doc.get_root_element().unwrap()))
+        .done();
+
+    assert!(matches!(result, Err(XmlSecError::Str(_))), "Should not place beside the root");
+
+    let result = doc.template()
+        .placement(XmlSecSignaturePlacement::XPath {expression: "//Missing".to_owned(), namespaces: Vec::new()})
+        .done();
+
+    assert!(matches!(result, Err(XmlSecError::NodeNotFound)));
+
+    // nothing got added along the way
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign6-doc.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_template_creation_with_ids()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .id("sig")
+        .signed_info_id("sig-signedinfo")
+        .keyinfo_id("sig-keyinfo")
+        .reference(XmlSecReferenceBuilder::new().uri("ReferencedID").id("sig-ref"))
+        .keyname(true)
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign2-tmpl-ids.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_template_creation_with_auto_ids()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign4-doc.xml")
+        .expect("Could not load template document");
+
+    let signature = doc.template()
+        .auto_ids(true)
+        .keyinfo_id("keyinfo")
+        .reference(XmlSecReferenceBuilder::new().uri("#body"))
+        .reference(XmlSecReferenceBuilder::new().uri("#ts"))
+        .reference(XmlSecReferenceBuilder::new().uri("").id("whole"))
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let mut xpath = XmlXPathContext::new(&doc)
+        .expect("Could not build xpath context");
+
+    xpath.register_namespace("dsig", "http://www.w3.org/2000/09/xmldsig#")
+        .expect("Could not register namespace");
+
+    let id_of = |xpath: &mut XmlXPathContext, search: &str| -> Vec<String> {
+        xpath.findnodes(search, None)
+            .expect("Could not find nodes")
+            .iter()
+            .map(|n| n.get_attribute("Id").expect("Node should have an Id"))
+            .collect()
+    };
+
+    let signature_id = signature.get_attribute("Id")
+        .expect("Signature should have an Id");
+
+    let signedinfo_ids = id_of(&mut xpath, "//dsig:SignedInfo");
+    let keyinfo_ids    = id_of(&mut xpath, "//dsig:KeyInfo");
+    let reference_ids  = id_of(&mut xpath, "//dsig:Reference");
+
+    assert!(signature_id.starts_with("Signature-"));
+    assert!(signedinfo_ids[0].starts_with("SignedInfo-"));
+    assert_eq!(keyinfo_ids, vec!["keyinfo"]);
+
+    assert_eq!(reference_ids.len(), 3);
+    assert!(reference_ids[0].starts_with("Reference-"));
+    assert!(reference_ids[1].starts_with("Reference-"));
+    assert_ne!(reference_ids[0], reference_ids[1]);
+    assert_eq!(reference_ids[2], "whole");
+}
+
+#[test]
+fn test_template_creation_with_keyinfo()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .uri("ReferencedID")
+        .keyinfo(XmlSecKeyInfoBuilder::new()
+            .keyname(Some("partner-key"))
+            .x509_issuer_serial()
+            .x509_subject_name()
+            .retrieval_method("#cert", Some("http://www.w3.org/2000/09/xmldsig#X509Data"))
+            .x509_ski()
+            .x509_certificate()
+            .x509_crl()
+            .keyvalue())
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign2-tmpl-keyinfo.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+#[cfg(feature = "openssl")]  // relies on OpenSSL specific methods or certificate handling
+fn test_encryption_template_creation()
+{
+    // load document
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    // build encrypted data node structure
+    let encdata = doc.encryption_template()
+        .method(XmlSecBlockCipher::Aes128Cbc)
+        .enctype(XmlSecEncryptionType::Element)
+        .id("ED1")
+        .mime_type("text/xml")
+        .keyname(true)
+        .encrypted_key(XmlSecKeyTransport::RsaOaep)
+        .encrypted_keyname(true)
+        .done()
+        .expect("Failed to build encryption template");
+
+    // template is not placed anywhere until used to encrypt
+    assert!(encdata.get_parent().is_none());
+
+    // compare template results
+    let reference = String::from_utf8(
+        include_bytes!("./resources/enc2-tmpl.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.node_to_string(&encdata), reference.trim_end());
+}
+
+#[test]
+fn test_encryption_template_standalone()
+{
+    let doc = XmlDocument::new()
+        .expect("Could not create empty document");
+
+    // build encrypted data node structure into an empty document
+    let encdata = doc.encryption_template()
+        .method(XmlSecBlockCipher::Aes256Cbc)
+        .mime_type("application/octet-stream")
+        .cipher_reference("cid:attachment")
+        .done()
+        .expect("Failed to build encryption template");
+
+    let root = doc.get_root_element()
+        .expect("Template should have become the document root");
+
+    assert_eq!(root, encdata);
+
+    // compare template results
+    let reference = String::from_utf8(
+        include_bytes!("./resources/enc3-tmpl.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
@@ -0,0 +1,521 @@
+//!
+//! Unit Tests for Encryption Context
+//!
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecSymmetricKeyKind;
+use xmlsec::XmlSecEncryptionContext;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecDecryptedData;
+use xmlsec::XmlSecBlockCipher;
+#[cfg(feature = "openssl")]
+use xmlsec::XmlSecKeyTransport;
+use xmlsec::XmlSecEncryptionTemplateBuilder;
+use xmlsec::XmlSecDocumentEncryptionTemplating;
+
+use libxml::parser::Parser           as XmlParser;
+use libxml::tree::document::Document as XmlDocument;
+
+
+#[test]
+fn test_enc_context_creation()
+{
+    XmlSecEncryptionContext::new();
+}
+
+
+#[test]
+fn test_enc_key_setting()
+{
+    let mut ctx = XmlSecEncryptionContext::new();
+
+    let key = common_load_key("key");
+
+    let key_ptr = unsafe { key.as_ptr() };
+
+    let oldkey = ctx.insert_key(key);
+
+    assert!(oldkey.is_none(), "It should never have been set at this point");
+
+    let newkey = ctx.release_key()
+        .expect("Should have had a set key now being released");
+
+    let newkey_ptr = unsafe { newkey.as_ptr() };
+
+    assert_eq!(key_ptr, newkey_ptr, "Key should have remained to be exactly the same");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_encrypt_node()
+{
+    let ctx = common_setup_context_and_key();
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let tmpl = XmlParser::default()
+        .parse_file("tests/resources/enc1-tmpl.xml")
+        .expect("Failed to load encryption template");
+
+    let root = doc.get_root_element()
+        .expect("Document should have a root");
+
+    let data = root.get_first_element_child()
+        .expect("Document should have a <Data/> node");
+
+    let encdata = tmpl.get_root_element()
+        .expect("Template should have a root");
+
+    if let Err(e) = ctx.encrypt_node(&encdata, &data) {
+        panic!("{}", e);
+    }
+
+    let result = doc.to_string();
+
+    assert!(!result.contains("Hello, World!"), "Plaintext should have been replaced");
+    assert!(result.contains("<EncryptedData xmlns=\"http://www.w3.org/2001/04/xmlenc#\" Type=\"http://www.w3.org/2001/04/xmlenc#Element\">"));
+
+    let child = root.get_first_element_child()
+        .expect("Document should have an <EncryptedData/> node");
+
+    assert_eq!(child.get_name(), "EncryptedData");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_encrypt_content()
+{
+    let ctx = common_setup_context_and_key();
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let tmpl = XmlParser::default()
+        .parse_file("tests/resources/enc1-tmpl.xml")
+        .expect("Failed to load encryption template");
+
+    let root = doc.get_root_element()
+        .expect("Document should have a root");
+
+    let encdata = tmpl.get_root_element()
+        .expect("Template should have a root");
+
+    if let Err(e) = ctx.encrypt_content(&encdata, &root) {
+        panic!("{}", e);
+    }
+
+    let result = doc.to_string();
+
+    assert!(!result.contains("Hello, World!"), "Plaintext should have been replaced");
+    assert!(result.contains("Type=\"http://www.w3.org/2001/04/xmlenc#Content\""));
+
+    let child = root.get_first_element_child()
+        .expect("Root should contain an <EncryptedData/> node");
+
+    assert_eq!(child.get_name(), "EncryptedData");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_encrypt_binary()
+{
+    let ctx = common_setup_context_and_key();
+
+    let tmpl = XmlParser::default()
+        .parse_file("tests/resources/enc1-tmpl.xml")
+        .expect("Failed to load encryption template");
+
+    let encdata = tmpl.get_root_element()
+        .expect("Template should have a root");
+
+    if let Err(e) = ctx.encrypt_binary(&encdata, b"Hello, World!") {
+        panic!("{}", e);
+    }
+
+    let cipher = tmpl.get_root_element()
+        .and_then(|r| r.get_last_element_child())
+        .and_then(|c| c.get_first_element_child())
+        .expect("Template should have a <CipherValue/> node");
+
+    assert!(!cipher.get_content().trim().is_empty(), "Cipher value should have been filled");
+}
+
+
+#[test]
+fn test_encrypt_without_key()
+{
+    let ctx = XmlSecEncryptionContext::new();
+
+    let tmpl = XmlParser::default()
+        .parse_file("tests/resources/enc1-tmpl.xml")
+        .expect("Failed to load encryption template");
+
+    let encdata = tmpl.get_root_element()
+        .expect("Template should have a root");
+
+    assert!(ctx.encrypt_binary(&encdata, b"Hello, World!").is_err());
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // relies on OpenSSL specific methods or certificate handling
+fn test_encrypt_node_built_template()
+{
+    let ctx = common_setup_context_and_key();
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let data = doc.get_root_element()
+        .and_then(|r| r.get_first_element_child())
+        .expect("Document should have a <Data/> node");
+
+    let tmpl = doc.encryption_template()
+        .method(XmlSecKeyTransport::RsaPkcs1)
+        .done()
+        .expect("Failed to build encryption template");
+
+    if let Err(e) = ctx.encrypt_node(&tmpl, &data) {
+        panic!("{}", e);
+    }
+
+    let parent = tmpl.get_parent()
+        .expect("Template should have taken the place of the encrypted node");
+
+    assert_eq!(parent.get_name(), "Envelope");
+    assert!(!doc.to_string().contains("Hello, World!"), "Plaintext should have been replaced");
+}
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_decrypt_document_element()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let root = doc.get_root_element()
+        .expect("Document should have a root");
+
+    let data = root.get_first_element_child()
+        .expect("Document should have a <Data/> node");
+
+    common_encrypt_with_template(|ctx, tmpl| ctx.encrypt_node(tmpl, &data));
+
+    let ctx = common_setup_context_and_key();
+
+    match ctx.decrypt_document(&doc)
+    {
+        Ok(XmlSecDecryptedData::Element(node)) => {
+            assert_eq!(node.get_name(), "Data");
+            assert_eq!(node.get_content().trim(), "Hello, World!");
+        }
+
+        Ok(other) => panic!("Should have decrypted into an element, got: {:?}", other),
+        Err(e)    => panic!("{}", e),
+    }
+
+    assert!(doc.to_string().contains("Hello, World!"), "Plaintext should be back in the document");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_decrypt_document_root()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let root = doc.get_root_element()
+        .expect("Document should have a root");
+
+    common_encrypt_with_template(|ctx, tmpl| ctx.encrypt_node(tmpl, &root));
+
+    let ctx = common_setup_context_and_key();
+
+    match ctx.decrypt_document(&doc)
+    {
+        Ok(XmlSecDecryptedData::Element(node)) => assert_eq!(node.get_name(), "Envelope"),
+        Ok(other) => panic!("Should have decrypted into an element, got: {:?}", other),
+        Err(e)    => panic!("{}", e),
+    }
+
+    let root = doc.get_root_element()
+        .expect("Document should have a root again");
+
+    assert_eq!(root.get_name(), "Envelope");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_decrypt_node_content()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let root = doc.get_root_element()
+        .expect("Document should have a root");
+
+    common_encrypt_with_template(|ctx, tmpl| ctx.encrypt_content(tmpl, &root));
+
+    let encdata = root.get_first_element_child()
+        .expect("Root should contain an <EncryptedData/> node");
+
+    let ctx = common_setup_context_and_key();
+
+    match ctx.decrypt_node(&encdata)
+    {
+        Ok(XmlSecDecryptedData::Content(node)) => {
+            assert_eq!(node.get_name(), "Envelope");
+
+            let data = node.get_first_element_child()
+                .expect("Decrypted content should contain the <Data/> node");
+
+            assert_eq!(data.get_name(), "Data");
+        }
+
+        Ok(other) => panic!("Should have decrypted into content, got: {:?}", other),
+        Err(e)    => panic!("{}", e),
+    }
+
+    assert!(encdata.is_null(), "Replaced encrypted data should have been released");
+}
+
+
+#[test]
+#[cfg(feature = "openssl")]  // GnuTLS and GCrypt lack RSA key transport, and NSS keeps no public key for PKCS#8 keys
+fn test_decrypt_node_binary()
+{
+    let tmpl = XmlParser::default()
+        .parse_file("tests/resources/enc1-tmpl.xml")
+        .expect("Failed to load encryption template");
+
+    let encdata = tmpl.get_root_element()
+        .expect("Template should have a root");
+
+    common_setup_context_and_key()
+        .encrypt_binary(&encdata, b"Hello, World!")
+        .expect("Failed to encrypt binary data");
+
+    let ctx = common_setup_context_and_key();
+
+    match ctx.decrypt_node(&encdata)
+    {
+        Ok(XmlSecDecryptedData::Binary(data)) => assert_eq!(data, b"Hello, World!"),
+        Ok(other) => panic!("Should have decrypted into binary data, got: {:?}", other),
+        Err(e)    => panic!("{}", e),
+    }
+}
+
+
+#[test]
+fn test_decrypt_reusing_context()
+{
+    let key = || XmlSecKey::from_symmetric(&[7u8; 32], XmlSecSymmetricKeyKind::Aes).expect("Failed to build key");
+
+    let mut ctx = XmlSecEncryptionContext::new();
+    ctx.insert_key(key());
+
+    let docs = (0..2).map(|_| common_encrypt_symmetric(key())).collect::<Vec<_>>();
+
+    for doc in docs
+    {
+        ctx.decrypt_document(&doc)
+            .expect("Failed to decrypt with a reused context");
+
+        assert!(doc.to_string().contains("Hello, World!"), "Plaintext should be back in the document");
+    }
+}
+
+
+#[test]
+fn test_decrypt_unlinked_node()
+{
+    let key = || XmlSecKey::from_symmetric(&[7u8; 32], XmlSecSymmetricKeyKind::Aes).expect("Failed to build key");
+
+    let mut ctx = XmlSecEncryptionContext::new();
+    ctx.insert_key(key());
+
+    let doc = common_encrypt_symmetric(key());
+
+    let mut encdata = doc.get_root_element()
+        .and_then(|r| r.get_first_element_child())
+        .expect("Document should have an <EncryptedData/> node");
+
+    encdata.unlink_node();
+
+    assert!(ctx.decrypt_node(&encdata).is_err(), "Detached encrypted data has no place to be decrypted into");
+}
+
+
+#[test]
+fn test_decrypt_document_without_encdata()
+{
+    let ctx = common_setup_context_and_key();
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document");
+
+    assert!(ctx.decrypt_document(&doc).is_err());
+}
+
+
+#[test]
+fn test_encrypt_decrypt_symmetric_key()
+{
+    for (method, kind, bytes) in [
+        (XmlSecBlockCipher::Aes256Cbc, XmlSecSymmetricKeyKind::Aes,  &[7u8; 32][..]),
+        (XmlSecBlockCipher::Des3Cbc,   XmlSecSymmetricKeyKind::Des3, &[9u8; 24][..]),
+    ]
+    {
+        let doc = XmlParser::default()
+            .parse_file("tests/resources/sign2-doc.xml")
+            .expect("Failed to load document to encrypt");
+
+        let data = doc.get_root_element()
+            .and_then(|r| r.get_first_element_child())
+            .expect("Document should have a <Data/> node");
+
+        let tmpl = doc.encryption_template()
+            .method(method)
+            .done()
+            .expect("Failed to build encryption template");
+
+        let mut ctx = XmlSecEncryptionContext::new();
+        ctx.insert_key(XmlSecKey::from_symmetric(bytes, kind).expect("Failed to build symmetric key"));
+
+        ctx.encrypt_node(&tmpl, &data)
+            .expect("Failed to encrypt with symmetric key");
+
+        assert!(! doc.to_string().contains("Hello, World!"), "Plaintext should have been replaced");
+
+        let mut ctx = XmlSecEncryptionContext::new();
+        ctx.insert_key(XmlSecKey::from_symmetric(bytes, kind).expect("Failed to build symmetric key"));
+
+        ctx.decrypt_document(&doc)
+            .expect("Failed to decrypt with symmetric key");
+
+        assert!(doc.to_string().contains("Hello, World!"), "Plaintext should be back in the document");
+    }
+}
+
+
+#[test]
+fn test_encrypt_reusing_context()
+{
+    let mut ctx = XmlSecEncryptionContext::new();
+    ctx.insert_key(XmlSecKey::from_symmetric(&[7u8; 32], XmlSecSymmetricKeyKind::Aes).expect("Failed to build key"));
+
+    for _ in 0..2
+    {
+        let doc = XmlParser::default()
+            .parse_file("tests/resources/sign2-doc.xml")
+            .expect("Failed to load document to encrypt");
+
+        let data = doc.get_root_element()
+            .and_then(|r| r.get_first_element_child())
+            .expect("Document should have a <Data/> node");
+
+        let text = data.get_first_child()
+            .expect("<Data/> should have text");
+
+        let tmpl = doc.encryption_template()
+            .method(XmlSecBlockCipher::Aes256Cbc)
+            .done()
+            .expect("Failed to build encryption template");
+
+        ctx.encrypt_node(&tmpl, &data)
+            .expect("Failed to encrypt with a reused context");
+
+        assert!(! doc.to_string().contains("Hello, World!"), "Plaintext should have been replaced");
+
+        // the replaced plaintext is released, leaving handles to it empty rather than dangling
+        assert!(data.is_null(), "Plaintext should have been released");
+        assert!(text.is_null(), "Plaintext children should have been released");
+    }
+}
+
+
+fn common_setup_context_and_key() -> XmlSecEncryptionContext
+{
+    let mut ctx = XmlSecEncryptionContext::new();
+
+    let key = common_load_key("key");
+
+    ctx.insert_key(key);
+
+    ctx
+}
+
+
+/// Loads the test document with its `<Data/>` node encrypted by the given AES-256 key.
+fn common_encrypt_symmetric(key: XmlSecKey) -> XmlDocument
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to encrypt");
+
+    let data = doc.get_root_element()
+        .and_then(|r| r.get_first_element_child())
+        .expect("Document should have a <Data/> node");
+
+    let tmpl = doc.encryption_template()
+        .method(XmlSecBlockCipher::Aes256Cbc)
+        .done()
+        .expect("Failed to build encryption template");
+
+    let mut ctx = XmlSecEncryptionContext::new();
+    ctx.insert_key(key);
+
+    ctx.encrypt_node(&tmpl, &data)
+        .expect("Failed to encrypt with symmetric key");
+
+    doc
+}
+
+
+#[cfg(feature = "openssl")]
+fn common_encrypt_with_template<F>(encrypt: F)
+    where F: FnOnce(&XmlSecEncryptionContext, &libxml::tree::Node) -> xmlsec::XmlSecResult<()>
+{
+    let ctx = common_setup_context_and_key();
+
+    let tmpl = XmlParser::default()
+        .parse_file("tests/resources/enc1-tmpl.xml")
+        .expect("Failed to load encryption template");
+
+    let encdata = tmpl.get_root_element()
+        .expect("Template should have a root");
+
+    encrypt(&ctx, &encdata)
+        .expect("Failed to encrypt");
+}
+
+
+/// Loads one of the RSA test keys, in a format the crypto backend reads: PEM is only supported by OpenSSL, while NSS
+/// takes PKCS#8 and GnuTLS and GCrypt take PKCS#1 DER.
+fn common_load_key(name: &str) -> XmlSecKey
+{
+    #[cfg(not(any(feature = "nss", feature = "gnutls", feature = "gcrypt")))]
+    let (path, format) = (format!("tests/resources/{}.pem", name), XmlSecKeyFormat::Pem);
+
+    #[cfg(feature = "nss")]
+    let (path, format) = (format!("tests/resources/{}.pkcs8.der", name), XmlSecKeyFormat::Der);
+
+    #[cfg(any(feature = "gnutls", feature = "gcrypt"))]
+    let (path, format) = (format!("tests/resources/{}.der", name), XmlSecKeyFormat::Der);
+
+    XmlSecKey::from_file(&path, format, None)
+        .expect("Failed to properly load key for test")
+}